@@ -0,0 +1,26 @@
+use std::env;
+use std::thread;
+use rusty_poker::equity::EquityMode;
+use rusty_poker::preflop::PreflopTable;
+use rusty_poker::simulation::Simulation;
+
+/// Runouts sampled per matchup unless another count is given
+const DEFAULT_TRIALS: u64 = 10_000;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let args = &args[1..];
+    if args.is_empty() || args.len() > 2 {
+        panic!("Usage: preflop-table output [trials | exact]")
+    }
+
+    // Sample every matchup unless asked to enumerate every runout, which is
+    // exact but takes days. Either way the threads share out the matchups.
+    let mode = match args.get(1).map(String::as_str) {
+        Some("exact") => EquityMode::Exact,
+        Some(trials) => EquityMode::MonteCarlo(Simulation::new(trials.parse().unwrap())),
+        None => EquityMode::MonteCarlo(Simulation::new(DEFAULT_TRIALS)),
+    };
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    PreflopTable::generate_with_threads(mode, threads).save(&args[0]).unwrap();
+}
@@ -0,0 +1,349 @@
+//! Remember exact equities so repeated matchups are only worked out once
+//!
+//! Situations are stored in a canonical form: suits are relabeled and players
+//! reordered so that every matchup that plays identically maps to the same
+//! entry. `AsKs` against `QhQd` on `7s2c3d` and `QcQs` against `AhKh` on
+//! `7h2d3s` share an entry, and each lookup hands the stored equities back
+//! in the caller's player order.
+
+use crate::card::*;
+use crate::equity::*;
+use crate::isomorphism::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Magic bytes at the start of a cache file
+const CACHE_MAGIC: &[u8; 4] = b"RPEC";
+const CACHE_VERSION: u8 = 1;
+
+/// Which entry a full cache drops to make room for a new one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Drop the entry that was looked up or added longest ago
+    LeastRecentlyUsed,
+    /// Drop the entry that was added longest ago, however often it has been
+    /// looked up since
+    FirstInFirstOut,
+}
+
+/// How well a cache has been doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that had to be calculated
+    pub misses: u64,
+    /// Entries dropped to make room for new ones
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups answered from the cache
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 }
+    }
+}
+
+struct CacheEntry {
+    /// Each player's equity in canonical player order
+    players: Vec<PlayerEquity>,
+    /// When the entry was added or, under least recently used eviction, last
+    /// looked up
+    stamp: u64,
+}
+
+/// A cache of exact equities in front of `multiway_equity`.
+///
+/// Only exact results are cached, since sampled results differ from run to
+/// run. A cache holds at most `capacity` situations and evicts according to
+/// its `EvictionPolicy` once full. It can be saved to a file and loaded
+/// again in a later run.
+///
+/// # Example
+/// ```
+/// use rusty_poker::cache::*;
+/// use rusty_poker::card::*;
+///
+/// let mut cache = EquityCache::new(1000);
+/// let board = [SEVEN_SPADES, TWO_CLUBS, THREE_DIAMONDS, NINE_HEARTS];
+/// let first = cache.multiway_equity(&[[ACE_SPADES, KING_SPADES], [QUEEN_HEARTS, QUEEN_DIAMONDS]], &board, &[]).unwrap();
+///
+/// // The same matchup with the suits relabeled and the players swapped
+/// let board = [SEVEN_HEARTS, TWO_DIAMONDS, THREE_SPADES, NINE_CLUBS];
+/// let second = cache.multiway_equity(&[[QUEEN_CLUBS, QUEEN_SPADES], [ACE_HEARTS, KING_HEARTS]], &board, &[]).unwrap();
+/// assert_eq!(first[0], second[1]);
+/// assert_eq!(cache.stats().hits, 1);
+/// ```
+pub struct EquityCache {
+    capacity: usize,
+    policy: EvictionPolicy,
+    entries: HashMap<Vec<u8>, CacheEntry>,
+    /// Keys by stamp, oldest first
+    order: BTreeMap<u64, Vec<u8>>,
+    clock: u64,
+    stats: CacheStats,
+}
+
+impl EquityCache {
+    /// An empty cache holding up to `capacity` situations, evicting the
+    /// least recently used once full
+    pub fn new(capacity: usize) -> EquityCache {
+        EquityCache {
+            capacity,
+            policy: EvictionPolicy::LeastRecentlyUsed,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Evict according to `policy` once the cache is full
+    pub fn policy(mut self, policy: EvictionPolicy) -> EquityCache {
+        self.policy = policy;
+        self
+    }
+
+    /// Each player's exact equity as computed by `multiway_equity` in
+    /// `EquityMode::Exact`, looked up in the cache if an equivalent situation
+    /// has been seen before
+    pub fn multiway_equity(&mut self, hands: &[HoleCards], board: &[Card], dead: &[Card]) -> Result<Vec<PlayerEquity>, EquityError> {
+        if hands.is_empty() {
+            return Err(EquityError::NoPlayers);
+        }
+        live_deck(hands, board, dead)?;
+        let (key, order) = canonical_key(hands, board, dead);
+        if let Some(stamp) = self.entries.get(&key).map(|entry| entry.stamp) {
+            self.stats.hits += 1;
+            if self.policy == EvictionPolicy::LeastRecentlyUsed {
+                self.order.remove(&stamp);
+                self.clock += 1;
+                self.order.insert(self.clock, key.clone());
+                self.entries.get_mut(&key).unwrap().stamp = self.clock;
+            }
+            let mut players = self.entries[&key].players.clone();
+            for (canonical, &player) in self.entries[&key].players.iter().zip(&order) {
+                players[player] = *canonical;
+            }
+            return Ok(players);
+        }
+
+        self.stats.misses += 1;
+        let report = multiway_equity(hands, board, dead, EquityMode::Exact)?;
+        self.insert(key, order.iter().map(|&player| report.players[player]).collect());
+        Ok(report.players)
+    }
+
+    /// Both players' exact equities, as `multiway_equity` with two hands
+    pub fn hand_vs_hand(&mut self, h1: &HoleCards, h2: &HoleCards, board: &[Card], dead: &[Card]) -> Result<[PlayerEquity; 2], EquityError> {
+        let players = self.multiway_equity(&[*h1, *h2], board, dead)?;
+        Ok([players[0], players[1]])
+    }
+
+    /// Hit, miss and eviction counts since the cache was created or loaded
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Number of situations in the cache
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop every entry, keeping the statistics
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    fn insert(&mut self, key: Vec<u8>, players: Vec<PlayerEquity>) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.entries.len() >= self.capacity {
+            let (_, oldest) = self.order.pop_first().unwrap();
+            self.entries.remove(&oldest);
+            self.stats.evictions += 1;
+        }
+        self.clock += 1;
+        self.order.insert(self.clock, key.clone());
+        self.entries.insert(key, CacheEntry{players, stamp: self.clock});
+    }
+
+    /// Write every entry, oldest first, in a compact binary format
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(CACHE_MAGIC)?;
+        writer.write_all(&[CACHE_VERSION])?;
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for key in self.order.values() {
+            let entry = &self.entries[key];
+            writer.write_all(&[key.len() as u8])?;
+            writer.write_all(key)?;
+            writer.write_all(&[entry.players.len() as u8])?;
+            for player in &entry.players {
+                for value in [player.pwin, player.ptie, player.equity, player.std_error] {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+            }
+        }
+        writer.flush()
+    }
+
+    /// Read a cache written by `write_to`, keeping the most recent `capacity`
+    /// entries. The statistics start from zero.
+    pub fn read_from<R: Read>(mut reader: R, capacity: usize) -> io::Result<EquityCache> {
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != CACHE_MAGIC || header[4] != CACHE_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not an equity cache file"));
+        }
+        let mut count = [0u8; 4];
+        reader.read_exact(&mut count)?;
+        let count = u32::from_le_bytes(count);
+        let mut cache = EquityCache::new(capacity);
+        let mut len = [0u8; 1];
+        let mut bytes = [0u8; 8];
+        for _ in 0..count {
+            reader.read_exact(&mut len)?;
+            let mut key = vec![0u8; len[0] as usize];
+            reader.read_exact(&mut key)?;
+            reader.read_exact(&mut len)?;
+            let mut players = Vec::with_capacity(len[0] as usize);
+            for _ in 0..len[0] {
+                let mut values = [0.0; 4];
+                for value in values.iter_mut() {
+                    reader.read_exact(&mut bytes)?;
+                    *value = f64::from_le_bytes(bytes);
+                }
+                players.push(PlayerEquity{pwin: values[0], ptie: values[1], equity: values[2], std_error: values[3]});
+            }
+            cache.insert(key, players);
+        }
+        cache.stats = CacheStats::default();
+        Ok(cache)
+    }
+
+    /// Write the cache to the file at `path`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Read a cache from the file at `path`, keeping the most recent
+    /// `capacity` entries
+    pub fn load<P: AsRef<Path>>(path: P, capacity: usize) -> io::Result<EquityCache> {
+        EquityCache::read_from(BufReader::new(File::open(path)?), capacity)
+    }
+}
+
+/// The canonical key of a situation, and for each player in canonical order
+/// the index of the hand it came from.
+///
+/// Under every suit relabeling each hand, the board and the dead cards are
+/// sorted by card index and the hands sorted among themselves. The smallest
+/// encoding wins, so isomorphic situations give the same key.
+fn canonical_key(hands: &[HoleCards], board: &[Card], dead: &[Card]) -> (Vec<u8>, Vec<usize>) {
+    let sorted = |cards: &mut dyn Iterator<Item = Card>| {
+        let mut indices: Vec<u8> = cards.map(|card| card.index() as u8).collect();
+        indices.sort_unstable();
+        indices
+    };
+    suit_permutations().iter()
+        .map(|perm| {
+            let mut players: Vec<(Vec<u8>, usize)> = hands.iter().enumerate()
+                .map(|(i, hand)| (sorted(&mut hand.iter().map(|card| permute_card(card, perm))), i))
+                .collect();
+            players.sort();
+            let mut key = vec![hands.len() as u8, board.len() as u8, dead.len() as u8];
+            key.extend(players.iter().flat_map(|(cards, _)| cards.iter().copied()));
+            key.extend(sorted(&mut board.iter().map(|card| permute_card(card, perm))));
+            key.extend(sorted(&mut dead.iter().map(|card| permute_card(card, perm))));
+            (key, players.into_iter().map(|(_, i)| i).collect())
+        })
+        .min()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    use super::*;
+
+    const BOARD: [Card; 4] = [SEVEN_SPADES, TWO_CLUBS, THREE_DIAMONDS, NINE_HEARTS];
+
+    #[test]
+    fn isomorphic_situations_share_an_entry() {
+        let mut cache = EquityCache::new(10);
+        let hands = [[ACE_SPADES, KING_SPADES], [QUEEN_HEARTS, QUEEN_DIAMONDS], [EIGHT_CLUBS, EIGHT_SPADES]];
+        let first = cache.multiway_equity(&hands, &BOARD, &[]).unwrap();
+        assert_eq!(first, multiway_equity(&hands, &BOARD, &[], EquityMode::Exact).unwrap().players);
+
+        // Rotate the players and swap spades with clubs
+        let perm = [Suit::Clubs, Suit::Spades, Suit::Hearts, Suit::Diamonds];
+        let relabel = |cards: &[Card]| -> Vec<Card> { cards.iter().map(|card| permute_card(card, &perm)).collect() };
+        let rotated: Vec<HoleCards> = [2, 0, 1].iter().map(|&i| {
+            let cards = relabel(&hands[i]);
+            [cards[1], cards[0]]
+        }).collect();
+        let second = cache.multiway_equity(&rotated, &relabel(&BOARD), &[]).unwrap();
+        assert_eq!(second, vec![first[2], first[0], first[1]]);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.stats(), CacheStats{hits: 1, misses: 1, evictions: 0});
+
+        // Dead cards are part of the situation
+        cache.multiway_equity(&hands, &BOARD, &[ACE_HEARTS]).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.multiway_equity(&hands, &BOARD, &[ACE_SPADES]), Err(EquityError::DuplicateCard(ACE_SPADES)));
+        assert_eq!(cache.stats().hit_rate(), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn eviction_policies() {
+        let matchups = [
+            [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS]],
+            [[ACE_SPADES, ACE_CLUBS], [QUEEN_SPADES, QUEEN_CLUBS]],
+            [[ACE_SPADES, ACE_CLUBS], [JACK_SPADES, JACK_CLUBS]],
+        ];
+        for (policy, kept) in [(EvictionPolicy::LeastRecentlyUsed, true), (EvictionPolicy::FirstInFirstOut, false)] {
+            let mut cache = EquityCache::new(2).policy(policy);
+            cache.multiway_equity(&matchups[0], &BOARD, &[]).unwrap();
+            cache.multiway_equity(&matchups[1], &BOARD, &[]).unwrap();
+            // Looking up the first matchup again only protects it under LRU
+            cache.multiway_equity(&matchups[0], &BOARD, &[]).unwrap();
+            cache.multiway_equity(&matchups[2], &BOARD, &[]).unwrap();
+            assert_eq!(cache.len(), 2);
+            assert_eq!(cache.stats().evictions, 1);
+            let hits = cache.stats().hits;
+            cache.multiway_equity(&matchups[0], &BOARD, &[]).unwrap();
+            assert_eq!(cache.stats().hits > hits, kept);
+        }
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let mut cache = EquityCache::new(10);
+        let hands = [[ACE_SPADES, KING_SPADES], [QUEEN_HEARTS, QUEEN_DIAMONDS]];
+        let equity = cache.multiway_equity(&hands, &BOARD, &[]).unwrap();
+        cache.multiway_equity(&hands, &BOARD[..3], &[]).unwrap();
+
+        let mut bytes = Vec::new();
+        cache.write_to(&mut bytes).unwrap();
+        let mut loaded = EquityCache::read_from(&bytes[..], 10).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.multiway_equity(&hands, &BOARD, &[]).unwrap(), equity);
+        assert_eq!(loaded.stats(), CacheStats{hits: 1, misses: 0, evictions: 0});
+
+        // Loading into a smaller cache keeps the most recent entries
+        let smaller = EquityCache::read_from(&bytes[..], 1).unwrap();
+        assert_eq!(smaller.len(), 1);
+        assert_eq!(smaller.stats().evictions, 0);
+
+        assert_eq!(EquityCache::read_from(&b"RPPT\x01"[..], 10).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -62,6 +62,13 @@ pub struct Card {
     pub suit: Suit
 }
 
+impl Card {
+    /// Index of this card in `all_cards()`, from 0 to 51
+    pub fn index(&self) -> usize {
+        (self.suit as usize) * 13 + (self.rank as usize - 2)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ParseCardError {
     ParseSuitError(String),
@@ -99,6 +106,14 @@ impl FromStr for Card {
     }
 }
 
+/// Parse a run of two character cards with no separators, such as `"AsKd7c"`
+pub fn parse_cards(s: &str) -> Result<Vec<Card>, ParseCardError> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return Err(ParseCardError::ParseFormatError(format!("Cards must be a run of two character cards: \"{}\"", s)));
+    }
+    (0..s.len()).step_by(2).map(|i| s[i..i + 2].parse()).collect()
+}
+
 pub type HoleCards = [Card; 2];
 
 pub fn ranks() -> [Rank; 13] {
@@ -114,12 +129,28 @@ pub fn all_cards() -> Vec<Card> {
     let mut cards : Vec<Card> = Vec::default();
     for suit in suits().iter() {
         for rank in ranks().iter() {
-            cards.push(Card{suit: suit.clone(), rank: *rank});
+            cards.push(Card{suit: *suit, rank: *rank});
         }
     }
     cards
 }
 
+/// Return a new Vec of every card that is not in `dead`
+pub fn remaining_cards(dead: &[Card]) -> Vec<Card> {
+    all_cards().into_iter().filter(|card| !dead.contains(card)).collect()
+}
+
+/// Return a new Vec of every two card combination that can be made from `cards`
+pub fn hole_card_combos(cards: &[Card]) -> Vec<HoleCards> {
+    let mut combos: Vec<HoleCards> = Vec::with_capacity(cards.len() * cards.len() / 2);
+    for (i, c1) in cards.iter().enumerate() {
+        for c2 in &cards[i + 1..] {
+            combos.push([*c1, *c2]);
+        }
+    }
+    combos
+}
+
 impl Ord for Rank {
     fn cmp(&self, other: &Self) -> Ordering {
         (*self as u32).cmp(&(*other as u32))
@@ -144,18 +175,6 @@ impl PartialOrd for Card {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::card::*;
-    #[test]
-    fn parse_card() {
-        assert_eq!(Card::from_str("Ac"), Ok(Card{suit: Suit::Clubs, rank: Rank::Ace}));
-        assert_eq!(Card::from_str("Ks"), Ok(Card{suit: Suit::Spades, rank: Rank::King}));
-        assert_eq!(Card::from_str("2d"), Ok(Card{suit: Suit::Diamonds, rank: Rank::Two}));
-        assert_eq!(Card::from_str("6h"), Ok(Card{suit: Suit::Hearts, rank: Rank::Six}));
-    }
-}
-
 #[used]
 pub static ACE_SPADES: Card = Card{rank: Rank::Ace, suit: Suit::Spades};
 #[used]
@@ -296,3 +315,39 @@ pub static FOUR: Rank = Rank::Four;
 pub static THREE: Rank = Rank::Three;
 #[used]
 pub static TWO: Rank = Rank::Two;
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    #[test]
+    fn parse_card() {
+        assert_eq!(Card::from_str("Ac"), Ok(Card{suit: Suit::Clubs, rank: Rank::Ace}));
+        assert_eq!(Card::from_str("Ks"), Ok(Card{suit: Suit::Spades, rank: Rank::King}));
+        assert_eq!(Card::from_str("2d"), Ok(Card{suit: Suit::Diamonds, rank: Rank::Two}));
+        assert_eq!(Card::from_str("6h"), Ok(Card{suit: Suit::Hearts, rank: Rank::Six}));
+    }
+
+    #[test]
+    fn parse_card_run() {
+        assert_eq!(parse_cards("AsKd7c"), Ok(vec![ACE_SPADES, KING_DIAMONDS, SEVEN_CLUBS]));
+        assert_eq!(parse_cards(""), Ok(vec![]));
+        assert!(parse_cards("AsK").is_err());
+        assert!(parse_cards("AsKx").is_err());
+    }
+
+    #[test]
+    fn combos() {
+        assert_eq!(hole_card_combos(&all_cards()).len(), 1326);
+        let remaining = remaining_cards(&[ACE_SPADES, KING_SPADES, QUEEN_SPADES]);
+        assert_eq!(remaining.len(), 49);
+        assert!(!remaining.contains(&KING_SPADES));
+        assert_eq!(hole_card_combos(&remaining).len(), 1176);
+    }
+
+    #[test]
+    fn card_index() {
+        for (i, card) in all_cards().iter().enumerate() {
+            assert_eq!(card.index(), i);
+        }
+    }
+}
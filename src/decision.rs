@@ -0,0 +1,241 @@
+//! Pot odds, fold equity and the expected value of calling or shoving
+
+/// A spot where hero faces a decision against a single villain.
+///
+/// `pot` holds the chips from earlier streets and any dead money such as antes
+/// or blinds of players who have folded. Chips either player has already put
+/// in on this street are held apart in `hero_invested` and `villain_invested`,
+/// and each stack is what the player has behind, not counting those chips.
+///
+/// Every expected value is measured in chips relative to hero folding now, so
+/// chips hero has already put in are sunk and never counted as a loss.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::decision::*;
+/// use rusty_poker::equity::*;
+///
+/// // Villain bets 75 into 100 on the turn and hero holds a straight draw
+/// let board = [NINE_CLUBS, EIGHT_DIAMONDS, TWO_SPADES, FOUR_HEARTS];
+/// let counts = hand_vs_hand_exact(&[JACK_HEARTS, TEN_HEARTS], &[ACE_HEARTS, ACE_DIAMONDS], &board, &[]);
+/// let equity = counts.exact_equities()[0].equity;
+/// let spot = Spot::new(100.0, 400.0, 400.0).invested(0.0, 75.0);
+/// assert_eq!(spot.break_even_equity(), 0.3);
+/// // Eight outs in 44 isn't enough to call
+/// assert!(spot.call_ev(equity) < 0.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spot {
+    /// Chips in the middle from earlier streets and dead money
+    pub pot: f64,
+    /// Chips hero has behind
+    pub hero_stack: f64,
+    /// Chips villain has behind
+    pub villain_stack: f64,
+    /// Chips hero has already put in on this street
+    pub hero_invested: f64,
+    /// Chips villain has already put in on this street
+    pub villain_invested: f64,
+}
+
+/// The expected value of shoving, split into what hero gains when villain
+/// folds and what hero gains when villain calls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShoveEv {
+    /// Chips won when villain folds, weighted by how often villain folds
+    pub fold_equity: f64,
+    /// Expected chips won when villain calls, weighted by how often villain
+    /// calls
+    pub called_ev: f64,
+    /// Total expected value of shoving
+    pub ev: f64,
+}
+
+/// Everything a spot says about a decision at once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    /// Chips hero must put in to call
+    pub to_call: f64,
+    /// Equity hero needs for a call to break even
+    pub break_even_equity: f64,
+    /// Expected value of calling
+    pub call_ev: f64,
+    /// How often hero must continue against the bet so a bluff can't profit
+    /// automatically
+    pub minimum_defense_frequency: f64,
+    /// Expected value of shoving all in
+    pub shove: ShoveEv,
+}
+
+impl Spot {
+    /// A spot with `pot` in the middle, nothing put in yet on this street and
+    /// the given stacks behind
+    ///
+    /// # Panics
+    /// If any amount is negative
+    pub fn new(pot: f64, hero_stack: f64, villain_stack: f64) -> Spot {
+        assert!(pot >= 0.0 && hero_stack >= 0.0 && villain_stack >= 0.0, "Chip amounts can't be negative");
+        Spot{pot, hero_stack, villain_stack, hero_invested: 0.0, villain_invested: 0.0}
+    }
+
+    /// Record the chips each player has already put in on this street
+    ///
+    /// # Panics
+    /// If either amount is negative
+    pub fn invested(mut self, hero: f64, villain: f64) -> Spot {
+        assert!(hero >= 0.0 && villain >= 0.0, "Chip amounts can't be negative");
+        self.hero_invested = hero;
+        self.villain_invested = villain;
+        self
+    }
+
+    /// The most either player can win from the other on this street: the
+    /// smaller of the two stacks, counting chips already put in on it
+    pub fn effective_stack(&self) -> f64 {
+        (self.hero_stack + self.hero_invested).min(self.villain_stack + self.villain_invested)
+    }
+
+    /// Chips hero must put in to call. When villain has bet more than hero
+    /// has behind, hero calls all in and the rest of the bet goes back to
+    /// villain.
+    pub fn to_call(&self) -> f64 {
+        (self.villain_invested - self.hero_invested).max(0.0).min(self.hero_stack)
+    }
+
+    /// Size of the pot once hero calls, leaving out any part of villain's
+    /// bet that hero can't cover
+    pub fn pot_after_call(&self) -> f64 {
+        let hero_total = self.hero_invested + self.to_call();
+        self.pot + hero_total + self.villain_invested.min(hero_total)
+    }
+
+    /// Equity hero needs for a call to break even: the call as a fraction of
+    /// the pot after calling
+    pub fn break_even_equity(&self) -> f64 {
+        let pot = self.pot_after_call();
+        if pot == 0.0 { 0.0 } else { self.to_call() / pot }
+    }
+
+    /// Expected chips won by calling with `equity`, which may come from
+    /// `hand_vs_hand`, a range calculation or anywhere else. Assumes no more
+    /// betting once hero has called.
+    pub fn call_ev(&self, equity: f64) -> f64 {
+        equity * self.pot_after_call() - self.to_call()
+    }
+
+    /// How often hero must continue against villain's bet so that villain
+    /// can't profit by betting any two cards. Only the part of the bet hero
+    /// can cover counts, since the rest is returned.
+    pub fn minimum_defense_frequency(&self) -> f64 {
+        let bet = self.to_call();
+        let before = self.pot + 2.0 * self.hero_invested;
+        if before + bet == 0.0 { 0.0 } else { before / (before + bet) }
+    }
+
+    /// Expected value of hero going all in when villain folds
+    /// `fold_frequency` of the time and otherwise calls, with hero holding
+    /// `equity` when called. With `None` villain always calls.
+    pub fn shove_ev(&self, equity: f64, fold_frequency: Option<f64>) -> ShoveEv {
+        let folds = fold_frequency.unwrap_or(0.0);
+        let fold_equity = folds * self.middle();
+        let called_ev = (1.0 - folds) * self.all_in_ev(equity);
+        ShoveEv{fold_equity, called_ev, ev: fold_equity + called_ev}
+    }
+
+    /// How often villain must fold for a shove with `equity` to break even.
+    /// Zero when the shove is profitable even if villain always calls.
+    pub fn break_even_fold_frequency(&self, equity: f64) -> f64 {
+        let called = self.all_in_ev(equity);
+        if called >= 0.0 { 0.0 } else { -called / (self.middle() - called) }
+    }
+
+    /// Work out every figure for a spot where hero holds `equity` and villain
+    /// folds to a shove `fold_frequency` of the time
+    pub fn decision(&self, equity: f64, fold_frequency: Option<f64>) -> Decision {
+        Decision {
+            to_call: self.to_call(),
+            break_even_equity: self.break_even_equity(),
+            call_ev: self.call_ev(equity),
+            minimum_defense_frequency: self.minimum_defense_frequency(),
+            shove: self.shove_ev(equity, fold_frequency),
+        }
+    }
+
+    /// Every chip in the middle right now, which hero wins if villain folds
+    fn middle(&self) -> f64 {
+        self.pot + self.hero_invested + self.villain_invested
+    }
+
+    /// Expected chips won by getting all in with `equity`. Both players end
+    /// up putting in the effective stack and the pot holds twice that plus
+    /// the chips from earlier streets.
+    fn all_in_ev(&self, equity: f64) -> f64 {
+        let effective = self.effective_stack();
+        equity * (self.pot + 2.0 * effective) - (effective - self.hero_invested)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn facing_a_bet() {
+        let spot = Spot::new(100.0, 200.0, 150.0).invested(0.0, 50.0);
+        assert_eq!(spot.to_call(), 50.0);
+        assert_eq!(spot.pot_after_call(), 200.0);
+        assert_eq!(spot.break_even_equity(), 0.25);
+        assert!((spot.call_ev(0.3) - 10.0).abs() < 1e-12);
+        assert_eq!(spot.call_ev(0.25), 0.0);
+        assert!((spot.minimum_defense_frequency() - 2.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn short_stack_calls_all_in() {
+        // Only 30 of villain's 50 can be called, and the rest goes back
+        let spot = Spot::new(100.0, 30.0, 500.0).invested(0.0, 50.0);
+        assert_eq!(spot.effective_stack(), 30.0);
+        assert_eq!(spot.to_call(), 30.0);
+        assert_eq!(spot.pot_after_call(), 160.0);
+        assert_eq!(spot.break_even_equity(), 30.0 / 160.0);
+        assert_eq!(spot.minimum_defense_frequency(), 100.0 / 130.0);
+    }
+
+    #[test]
+    fn facing_a_raise() {
+        // Hero bet 20 and villain raised to 60 with 10 of antes in the pot
+        let spot = Spot::new(10.0, 180.0, 140.0).invested(20.0, 60.0);
+        assert_eq!(spot.effective_stack(), 200.0);
+        assert_eq!(spot.to_call(), 40.0);
+        assert_eq!(spot.pot_after_call(), 130.0);
+        assert_eq!(spot.break_even_equity(), 40.0 / 130.0);
+        // Villain risks 40 more to win the 50 in the middle before the raise
+        assert_eq!(spot.minimum_defense_frequency(), 50.0 / 90.0);
+    }
+
+    #[test]
+    fn shoving() {
+        let spot = Spot::new(100.0, 200.0, 150.0);
+        assert_eq!(spot.effective_stack(), 150.0);
+        // Called, hero puts in 150 to win a pot of 400 with 40% equity
+        let called = spot.shove_ev(0.4, None);
+        assert_eq!(called.fold_equity, 0.0);
+        assert!((called.ev - 10.0).abs() < 1e-12);
+        let half = spot.shove_ev(0.4, Some(0.5));
+        assert!((half.fold_equity - 50.0).abs() < 1e-12);
+        assert!((half.called_ev - 5.0).abs() < 1e-12);
+        assert!((half.ev - 55.0).abs() < 1e-12);
+        assert_eq!(spot.break_even_fold_frequency(0.4), 0.0);
+
+        // With 20% equity calling costs hero 70, so villain must fold 70 / 170
+        assert!((spot.break_even_fold_frequency(0.2) - 70.0 / 170.0).abs() < 1e-12);
+        assert!(spot.shove_ev(0.2, Some(70.0 / 170.0)).ev.abs() < 1e-9);
+
+        // Chips already put in are sunk and the effective stack counts them
+        let raised = Spot::new(10.0, 180.0, 140.0).invested(20.0, 60.0);
+        let decision = raised.decision(0.5, Some(0.0));
+        assert_eq!(decision.shove.ev, 0.5 * 410.0 - 180.0);
+        assert_eq!(decision.to_call, 40.0);
+    }
+}
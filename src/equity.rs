@@ -1,51 +1,1974 @@
 use crate::card::*;
+use crate::frequency::*;
 use crate::hand_type::*;
-use rand::thread_rng;
+use crate::range::*;
+use crate::showdown::*;
+use crate::simulation::*;
+use crate::util::*;
+use crate::variant::{variant_equity_monitored, variant_live_deck, Holdem, PokerVariant};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use std::cmp::Ordering;
+use std::time::{Duration, Instant};
 
-/// Hand equity for a given hand or range played against another hand or range
+/// Hand equity for a given hand or range played against another hand or range.
+///
+/// Kept for compatibility. `EquityCounts` carries the raw counts behind these
+/// probabilities in double precision.
 pub struct HandEquity {
     /// Probability of winning
     pub pwin: f32,
     /// Probability of drawing
-    pub pdraw: f32
+    pub pdraw: f32,
 }
 
-pub fn hand_vs_hand(h1: &HoleCards, h2: &HoleCards, board: &Vec<Card>, num_trials: u32) -> [HandEquity; 2]
+/// A single player's share of a multiway pot
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PlayerEquity {
+    /// Probability of winning the whole pot
+    pub pwin: f64,
+    /// Probability of splitting the pot with at least one other player
+    pub ptie: f64,
+    /// Expected share of the pot, with split pots credited fractionally
+    pub equity: f64,
+    /// Standard error of `equity`, or zero if every runout was enumerated
+    pub std_error: f64,
+}
+
+impl PlayerEquity {
+    /// 95% confidence interval for `equity`, clamped to `[0, 1]`
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        confidence_interval(self.equity, self.std_error)
+    }
+}
+
+/// Raw showdown results for one player.
+///
+/// Split pots are counted in `ties`, and the fraction of the pot the player
+/// took in each of them is added to `tie_share`, so a three way chop adds one
+/// tie and a third of a pot.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::*;
+///
+/// // The board plays for everyone
+/// let hands = [[TWO_CLUBS, THREE_CLUBS], [TWO_DIAMONDS, THREE_DIAMONDS], [TWO_HEARTS, THREE_HEARTS]];
+/// let board = vec![ACE_SPADES, KING_SPADES, QUEEN_HEARTS, JACK_DIAMONDS, TEN_CLUBS];
+/// let report = multiway_equity(&hands, &board, &[], EquityMode::Exact).unwrap();
+/// let counts = report.counts[0];
+/// assert_eq!((counts.wins, counts.ties, counts.losses, counts.trials), (0, 1, 0, 1));
+/// assert!((counts.tie_share - 1.0 / 3.0).abs() < 1e-12);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EquityCounts {
+    /// Showdowns won outright
+    pub wins: u64,
+    /// Showdowns in which the pot was split with at least one other player
+    pub ties: u64,
+    /// Showdowns lost
+    pub losses: u64,
+    /// Number of showdowns
+    pub trials: u64,
+    /// Total fraction of a pot won across every split pot
+    pub tie_share: f64,
+    /// Sum of the squared pot shares, for the standard error
+    share_sq: f64,
+}
+
+impl EquityCounts {
+    /// Record one showdown in which the player won `share` of the pot
+    pub fn record(&mut self, share: f64, split: bool) {
+        if share == 0.0 {
+            self.losses += 1;
+        } else if split {
+            self.ties += 1;
+            self.tie_share += share;
+        } else {
+            self.wins += 1;
+        }
+        self.trials += 1;
+        self.share_sq += share * share;
+    }
+
+    /// Probability of winning the whole pot
+    pub fn pwin(&self) -> f64 {
+        self.wins as f64 / self.trials as f64
+    }
+
+    /// Probability of splitting the pot
+    pub fn ptie(&self) -> f64 {
+        self.ties as f64 / self.trials as f64
+    }
+
+    /// Probability of losing
+    pub fn ploss(&self) -> f64 {
+        self.losses as f64 / self.trials as f64
+    }
+
+    /// Expected share of the pot: every win plus the shares of every split pot
+    pub fn equity(&self) -> f64 {
+        (self.wins as f64 + self.tie_share) / self.trials as f64
+    }
+
+    /// Standard error of `equity()`, treating the showdowns as a random
+    /// sample
+    pub fn std_error(&self) -> f64 {
+        if self.trials < 2 {
+            return 0.0;
+        }
+        let n = self.trials as f64;
+        let mean = self.equity();
+        let variance = ((self.share_sq - n * mean * mean) / (n - 1.0)).max(0.0);
+        (variance / n).sqrt()
+    }
+
+    /// Summarize the counts as probabilities
+    pub fn player_equity(&self) -> PlayerEquity {
+        PlayerEquity{pwin: self.pwin(), ptie: self.ptie(), equity: self.equity(), std_error: self.std_error()}
+    }
+
+    /// Convert to the older `HandEquity`, which keeps only the win and tie
+    /// probabilities in single precision
+    pub fn hand_equity(&self) -> HandEquity {
+        HandEquity{pwin: self.pwin() as f32, pdraw: self.ptie() as f32}
+    }
+
+    fn estimate(&self) -> EquityEstimate {
+        EquityEstimate{equity: self.player_equity(), samples: self.trials}
+    }
+}
+
+impl Merge for EquityCounts {
+    fn merge(&mut self, other: EquityCounts) {
+        self.wins += other.wins;
+        self.ties += other.ties;
+        self.losses += other.losses;
+        self.trials += other.trials;
+        self.tie_share += other.tie_share;
+        self.share_sq += other.share_sq;
+    }
+}
+
+/// Number of standard errors on either side of an estimate that a 95%
+/// confidence interval covers
+pub const CONFIDENCE_Z: f64 = 1.96;
+
+fn confidence_interval(equity: f64, std_error: f64) -> (f64, f64) {
+    let half_width = CONFIDENCE_Z * std_error;
+    ((equity - half_width).max(0.0), (equity + half_width).min(1.0))
+}
+
+/// A sampled equity and how many showdowns it is based on
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EquityEstimate {
+    pub equity: PlayerEquity,
+    /// Number of showdowns the estimate is based on
+    pub samples: u64,
+}
+
+/// Every player's equity along with how much work it took to compute
+#[derive(Debug, Clone, PartialEq)]
+pub struct EquityReport {
+    /// Each player's equity, in the order the hands were given
+    pub players: Vec<PlayerEquity>,
+    /// Each player's raw showdown counts, in the order the hands were given.
+    /// When folded hands are enumerated exactly, these add up the runouts of
+    /// every deal without the folded combos' weights, which only `players`
+    /// accounts for.
+    pub counts: Vec<EquityCounts>,
+    /// Number of runouts that were shown down
+    pub trials: u64,
+    /// Wall clock time spent on the calculation
+    pub elapsed: Duration,
+    /// True if the calculation was cancelled and covers only the trials run
+    /// before then
+    pub cancelled: bool,
+}
+
+/// A snapshot of a calculation that is still running
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// Number of runouts or deals shown down so far
+    pub trials: u64,
+    /// Number of trials the calculation will run if it isn't stopped early
+    pub planned: u64,
+    /// Each player's equity over the trials so far
+    pub players: Vec<PlayerEquity>,
+}
+
+/// A callback that receives progress reports
+type ProgressFn<'a> = Box<dyn FnMut(&Progress) + 'a>;
+
+/// Watches a long running equity calculation, reporting its progress and
+/// letting it be cancelled.
+///
+/// Progress is reported on the calling thread about a hundred times over a
+/// calculation, spaced by the number of trials done, and once more when it
+/// finishes. Cancellation is checked after every runout of an exact
+/// calculation and every batch of a sampled one. A cancelled calculation
+/// returns what it has so far with `cancelled` set on its result.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::*;
+/// use rusty_poker::simulation::{CancelToken, Simulation};
+///
+/// let token = CancelToken::new();
+/// let stopper = token.clone();
+/// let mut monitor = Monitor::new().cancel_token(token).on_progress(|progress| {
+///     if progress.trials >= 2000 {
+///         stopper.cancel();
+///     }
+/// });
+/// let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS]];
+/// let mode = EquityMode::MonteCarlo(Simulation::new(1_000_000));
+/// let result = multiway_equity_monitored(&hands, &[], &[], mode, &mut monitor).unwrap();
+/// assert!(result.cancelled);
+/// assert!(result.trials < 1_000_000);
+/// ```
+#[derive(Default)]
+pub struct Monitor<'a> {
+    progress: Option<ProgressFn<'a>>,
+    cancel: Option<CancelToken>,
+    pub(crate) stopped: bool,
+    /// Number of trials covered by the last progress report
+    reported: u64,
+}
+
+impl<'a> Monitor<'a> {
+    /// A monitor that reports to nobody and never cancels
+    pub fn new() -> Monitor<'a> {
+        Monitor::default()
+    }
+
+    /// Call `f` with the current estimate after every batch of trials
+    pub fn on_progress<F: FnMut(&Progress) + 'a>(mut self, f: F) -> Monitor<'a> {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    /// Stop the calculation once `token` is cancelled
+    pub fn cancel_token(mut self, token: CancelToken) -> Monitor<'a> {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Report progress if enough trials have been done since the last report
+    /// and return true if the calculation should carry on
+    pub(crate) fn update<P: FnOnce() -> Vec<PlayerEquity>>(&mut self, trials: u64, planned: u64, players: P) -> bool {
+        if trials.saturating_sub(self.reported) >= planned.div_ceil(PROGRESS_BATCHES).max(1) {
+            self.report(trials, planned, players);
+        }
+        if self.cancel.as_ref().is_some_and(|token| token.is_cancelled()) {
+            self.stopped = true;
+        }
+        !self.stopped
+    }
+
+    /// Report the finished calculation's `trials`, unless the last report
+    /// already covered them, and get ready for the next calculation
+    pub(crate) fn finish<P: FnOnce() -> Vec<PlayerEquity>>(&mut self, trials: u64, planned: u64, players: P) {
+        if trials != self.reported {
+            self.report(trials, planned, players);
+        }
+        self.reported = 0;
+    }
+
+    fn report<P: FnOnce() -> Vec<PlayerEquity>>(&mut self, trials: u64, planned: u64, players: P) {
+        if let Some(progress) = self.progress.as_mut() {
+            progress(&Progress{trials, planned, players: players()});
+        }
+        self.reported = trials;
+    }
+}
+
+/// Calculations report progress about this many times, and Monte Carlo runs
+/// sample in about this many batches
+const PROGRESS_BATCHES: u64 = 100;
+
+/// When an adaptive simulation may stop sampling.
+///
+/// # Example
+/// ```
+/// use rusty_poker::equity::*;
+/// use std::time::Duration;
+///
+/// // Every equity to within half a percent, or as close as we get in a second
+/// let precision = Precision::new(0.005).time_budget(Duration::from_secs(1));
+/// assert_eq!(precision.batch, 1000);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Precision {
+    /// Stop once every player's 95% confidence interval reaches at most this
+    /// far on either side of their equity
+    pub half_width: f64,
+    /// Stop once this much time has passed, even if the target precision
+    /// hasn't been reached
+    pub time_budget: Option<Duration>,
+    /// Number of trials between checks of the stopping rule
+    pub batch: u64,
+}
+
+impl Precision {
+    /// Sample until every confidence interval is within `half_width` of the
+    /// estimate, checking every 1000 trials
+    pub fn new(half_width: f64) -> Precision {
+        Precision{half_width, time_budget: None, batch: 1000}
+    }
+
+    /// Stop after `budget` has elapsed
+    pub fn time_budget(mut self, budget: Duration) -> Precision {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// Check the stopping rule every `batch` trials
+    pub fn batch(mut self, batch: u64) -> Precision {
+        self.batch = batch.max(1);
+        self
+    }
+
+    /// True once `spread`'s confidence intervals are narrow enough or the time
+    /// budget has run out
+    fn reached<A: Spread>(&self, spread: &A, elapsed: Duration) -> bool {
+        CONFIDENCE_Z * spread.max_std_error() <= self.half_width
+            || self.time_budget.is_some_and(|budget| elapsed >= budget)
+    }
+}
+
+/// How the runouts of an equity calculation are chosen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EquityMode {
+    /// Show down every possible runout
+    Exact,
+    /// Show down random runouts as configured by the simulation
+    MonteCarlo(Simulation),
+    /// Show down random runouts until the target precision is reached, the
+    /// time budget runs out or `trials` runouts of the simulation have been
+    /// shown down, whichever comes first
+    Adaptive(Simulation, Precision),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EquityError {
+    /// A card appears more than once among the hands, board and dead cards
+    DuplicateCard(Card),
+    /// The board has more than five cards
+    InvalidBoard(usize),
+    /// No hands were given
+    NoPlayers,
+    /// Every combo in a range is blocked or has no weight, or the ranges
+    /// can't be dealt together without sharing a card
+    EmptyRange,
+    /// The board can't be run this many times: either zero times, or more
+    /// times than there are cards left to deal
+    InvalidRuns(usize),
+    /// No boards were given
+    NoBoards,
+    /// There aren't enough cards left to complete this many boards
+    TooManyBoards(usize),
+    /// A hand has the wrong number of hole cards for the game
+    InvalidHand(usize),
+    /// A card isn't part of the game's deck
+    CardNotInDeck(Card),
+    /// A sampling mode was asked for zero trials
+    NoTrials,
+    /// An exact range calculation would need this many showdowns, more than
+    /// `MAX_EXACT_SHOWDOWNS`
+    TooManyShowdowns(u64),
+}
+
+/// The equity associated with a single combo of a range. What the equity
+/// measures depends on the calculation that produced it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComboEquity {
+    pub cards: HoleCards,
+    /// The combo's weight in its range
+    pub weight: f64,
+    pub equity: PlayerEquity,
+}
+
+/// Hero's equity against a range, overall and against each combo
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandVsRangeEquity {
+    /// Hero's equity against the whole range
+    pub equity: PlayerEquity,
+    /// Hero's equity against each combo not blocked by hero or the board
+    pub combos: Vec<ComboEquity>,
+    /// Number of runouts shown down across every combo
+    pub trials: u64,
+    /// Wall clock time spent on the calculation
+    pub elapsed: Duration,
+    /// True if the calculation was cancelled before every combo was finished
+    pub cancelled: bool,
+}
+
+/// Exact equity of two ranges against each other
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeVsRangeEquity {
+    /// Each player's equity against the other player's whole range
+    pub equity: [PlayerEquity; 2],
+    /// For each player, the equity of every combo in their range that isn't
+    /// blocked by the board, against the other player's whole range
+    pub combos: [Vec<ComboEquity>; 2],
+    /// True if the calculation was cancelled before every runout was walked
+    pub cancelled: bool,
+}
+
+/// `hand_vs_hand` enumerates every runout instead of sampling when there are
+/// at most this many runouts left to deal.
+pub const EXACT_RUNOUT_THRESHOLD: u64 = 20_000;
+
+/// The most showdowns `range_vs_range` will enumerate, counting one for every
+/// runout of every pair of combos that can be dealt together. Full ranges on
+/// the flop come to about one and a half billion, so at this size the calculation
+/// takes around ten seconds in an optimized build.
+pub const MAX_EXACT_SHOWDOWNS: u64 = 500_000_000;
+
+/// Raw showdown counts from a heads up matchup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HeadsUpCounts {
+    pub p1_wins: u64,
+    pub p2_wins: u64,
+    pub ties: u64,
+}
+
+impl HeadsUpCounts {
+    /// Number of runouts that were shown down
+    pub fn total(&self) -> u64 {
+        self.p1_wins + self.p2_wins + self.ties
+    }
+
+    /// Convert the counts into each player's probability of winning and
+    /// drawing
+    pub fn equities(&self) -> [HandEquity; 2] {
+        let total = self.total() as f32;
+        [HandEquity{pwin: self.p1_wins as f32 / total, pdraw: self.ties as f32 / total},
+         HandEquity{pwin: self.p2_wins as f32 / total, pdraw: self.ties as f32 / total}]
+    }
+
+    /// Each player's counts, with every tie worth half a pot
+    pub fn player_counts(&self) -> [EquityCounts; 2] {
+        let ties = self.ties as f64;
+        let counts = |wins: u64, losses: u64| EquityCounts{
+            wins,
+            ties: self.ties,
+            losses,
+            trials: self.total(),
+            tie_share: ties / 2.0,
+            share_sq: wins as f64 + ties / 4.0,
+        };
+        [counts(self.p1_wins, self.p2_wins), counts(self.p2_wins, self.p1_wins)]
+    }
+
+    /// Each player's share of the counted runouts, treating the counts as
+    /// every runout rather than a sample
+    pub fn exact_equities(&self) -> [PlayerEquity; 2] {
+        let total = self.total() as f64;
+        let ptie = self.ties as f64 / total;
+        let equity = |wins: u64| {
+            let pwin = wins as f64 / total;
+            PlayerEquity{pwin, ptie, equity: pwin + ptie / 2.0, std_error: 0.0}
+        };
+        [equity(self.p1_wins), equity(self.p2_wins)]
+    }
+
+    pub(crate) fn record(&mut self, h1: &HoleCards, h2: &HoleCards, board: &[Card]) {
+        match showdown(&[*h1, *h2], board).winners() {
+            [0] => self.p1_wins += 1,
+            [1] => self.p2_wins += 1,
+            _ => self.ties += 1
+        }
+    }
+}
+
+impl Spread for HeadsUpCounts {
+    /// Standard error of either player's pot share, which is the same for both
+    /// since the shares always sum to one
+    fn max_std_error(&self) -> f64 {
+        let n = self.total() as f64;
+        if n < 2.0 {
+            return 0.0;
+        }
+        let mean = (self.p1_wins as f64 + self.ties as f64 / 2.0) / n;
+        let mean_sq = (self.p1_wins as f64 + self.ties as f64 / 4.0) / n;
+        let variance = ((mean_sq - mean * mean) * n / (n - 1.0)).max(0.0);
+        (variance / n).sqrt()
+    }
+}
+
+impl Merge for HeadsUpCounts {
+    fn merge(&mut self, other: HeadsUpCounts) {
+        self.p1_wins += other.p1_wins;
+        self.p2_wins += other.p2_wins;
+        self.ties += other.ties;
+    }
+}
+
+/// Compute each hand's probability of winning and drawing.
+///
+/// Cards in `dead` are removed from the deck before any runouts are dealt.
+/// When at most `EXACT_RUNOUT_THRESHOLD` runouts (or at most `num_trials`,
+/// whichever is larger) remain, every runout is enumerated and the result is
+/// exact. Otherwise `num_trials` random runouts are sampled, across every
+/// available core if there are at least `MIN_PARALLEL_TRIALS` of them. Use
+/// `hand_vs_hand_report` to choose the threads and seed.
+///
+/// # Panics
+/// If a card is used more than once or the board has more than five cards
+pub fn hand_vs_hand(h1: &HoleCards, h2: &HoleCards, board: &[Card], dead: &[Card], num_trials: u32) -> [HandEquity; 2]
 {
-    let mut cards = all_cards();
-    let mut dead_cards: Vec<Card> = Vec::default();
-    let mut board: Vec<Card> = board.clone();
-    dead_cards.extend_from_slice(h1);
-    dead_cards.extend_from_slice(h2);
-    for card in dead_cards {
-        cards.remove(cards.iter().position(|x| *x == card).unwrap());
+    let deck = live_deck(&[*h1, *h2], board, dead).expect("Invalid cards");
+    let mode = heads_up_mode(&deck, board, &Simulation::new(num_trials as u64).all_cores());
+    let counts = heads_up_counts(h1, h2, board, &deck, mode, &mut Monitor::new());
+    counts.player_counts().map(|counts| counts.hand_equity())
+}
+
+/// Compute both hands' equities as `hand_vs_hand` does, along with the
+/// counts, standard errors and number of runouts behind them. Runouts are
+/// sampled according to `sim` when there are more than
+/// `EXACT_RUNOUT_THRESHOLD` and more than `sim.trials` of them, and
+/// enumerated otherwise.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::*;
+/// use rusty_poker::simulation::Simulation;
+///
+/// let sim = Simulation::new(10000).threads(2).seed(7);
+/// let report = hand_vs_hand_report(&[ACE_SPADES, ACE_CLUBS], &[KING_SPADES, KING_CLUBS], &[], &[], &sim).unwrap();
+/// assert_eq!(report.trials, 10000);
+/// let (low, high) = report.players[0].confidence_interval();
+/// assert!(high - low < 0.02);
+/// ```
+pub fn hand_vs_hand_report(h1: &HoleCards, h2: &HoleCards, board: &[Card], dead: &[Card], sim: &Simulation) -> Result<EquityReport, EquityError> {
+    hand_vs_hand_report_monitored(h1, h2, board, dead, sim, &mut Monitor::new())
+}
+
+/// Compute both hands' equities as `hand_vs_hand_report` does, reporting
+/// progress to `monitor` and stopping early if it is cancelled
+pub fn hand_vs_hand_report_monitored(h1: &HoleCards, h2: &HoleCards, board: &[Card], dead: &[Card], sim: &Simulation, monitor: &mut Monitor) -> Result<EquityReport, EquityError> {
+    let start = Instant::now();
+    let deck = live_deck(&[*h1, *h2], board, dead)?;
+    let mode = heads_up_mode(&deck, board, sim);
+    check_trials(mode)?;
+    let counts = heads_up_counts(h1, h2, board, &deck, mode, monitor);
+    let players = if mode == EquityMode::Exact && !monitor.stopped {
+        counts.exact_equities()
+    } else {
+        counts.player_counts().map(|counts| counts.player_equity())
+    };
+    Ok(EquityReport{players: players.to_vec(), counts: counts.player_counts().to_vec(), trials: counts.total(), elapsed: start.elapsed(), cancelled: monitor.stopped})
+}
+
+/// Enumerate the runouts of `board` from `deck` if there are at most
+/// `EXACT_RUNOUT_THRESHOLD` or at most `sim.trials` of them, and sample them
+/// otherwise
+fn heads_up_mode(deck: &[Card], board: &[Card], sim: &Simulation) -> EquityMode {
+    let runouts = num_combinations(deck.len(), 5 - board.len());
+    if runouts <= EXACT_RUNOUT_THRESHOLD.max(sim.trials) { EquityMode::Exact } else { EquityMode::MonteCarlo(*sim) }
+}
+
+/// Show down the runouts of `board` from `deck` according to `mode`,
+/// reporting progress to `monitor`
+fn heads_up_counts(h1: &HoleCards, h2: &HoleCards, board: &[Card], deck: &[Card], mode: EquityMode, monitor: &mut Monitor) -> HeadsUpCounts {
+    let planned = planned_trials(deck.len(), 5 - board.len(), mode);
+    let equities = |counts: &HeadsUpCounts| counts.player_counts().map(|counts| counts.player_equity()).to_vec();
+    let counts = walk_runouts(deck, board, mode, HeadsUpCounts::default, |counts, full_board| counts.record(h1, h2, full_board),
+        |counts| monitor.update(counts.total(), planned, || equities(counts)));
+    monitor.finish(counts.total(), planned, || equities(&counts));
+    counts
+}
+
+/// Show down every possible runout of `board` and count the results, with
+/// the cards in `dead` removed from the deck.
+///
+/// # Panics
+/// If a card is used more than once or the board has more than five cards
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::*;
+///
+/// // Open-ended straight draw against an overpair on the turn
+/// let board = vec![NINE_SPADES, EIGHT_DIAMONDS, TWO_CLUBS, FOUR_HEARTS];
+/// let counts = hand_vs_hand_exact(&[JACK_HEARTS, TEN_HEARTS], &[ACE_CLUBS, ACE_DIAMONDS], &board, &[]);
+/// assert_eq!(counts.total(), 44);
+/// assert_eq!(counts.p1_wins, 8);
+/// ```
+pub fn hand_vs_hand_exact(h1: &HoleCards, h2: &HoleCards, board: &[Card], dead: &[Card]) -> HeadsUpCounts {
+    let cards = live_deck(&[*h1, *h2], board, dead).expect("Invalid cards");
+    walk_runouts(&cards, board, EquityMode::Exact, HeadsUpCounts::default, |counts, full_board| counts.record(h1, h2, full_board), |_| true)
+}
+
+/// Compute every player's equity in a multiway pot.
+///
+/// Split pots are credited fractionally, so a three way chop adds a third of
+/// the pot to each player's `equity`. Cards in `dead` are removed from the
+/// deck before any runouts are dealt. Sampled equities come with standard
+/// errors, and exact ones have a standard error of zero.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::*;
+/// use rusty_poker::simulation::Simulation;
+///
+/// let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS], [QUEEN_SPADES, QUEEN_CLUBS]];
+/// let board = vec![TWO_HEARTS, SEVEN_DIAMONDS, NINE_CLUBS, FOUR_HEARTS];
+/// let result = multiway_equity(&hands, &board, &[], EquityMode::Exact).unwrap();
+/// assert!(result.players[0].equity > result.players[1].equity);
+/// assert_eq!(result.trials, 42);
+///
+/// // Sample preflop until each equity is known to within a percent
+/// let mode = EquityMode::Adaptive(Simulation::new(1_000_000), Precision::new(0.01));
+/// let result = multiway_equity(&hands, &[], &[], mode).unwrap();
+/// let (low, high) = result.players[0].confidence_interval();
+/// assert!(high - low <= 0.02);
+/// ```
+pub fn multiway_equity(hands: &[HoleCards], board: &[Card], dead: &[Card], mode: EquityMode) -> Result<EquityReport, EquityError> {
+    multiway_equity_monitored(hands, board, dead, mode, &mut Monitor::new())
+}
+
+/// Compute every player's equity in a multiway pot as `multiway_equity`
+/// does, reporting progress to `monitor` and stopping early if it is
+/// cancelled.
+///
+/// Exact enumeration walks the runouts in deck order, so the partial result
+/// of a cancelled exact calculation is not a random sample of the runouts.
+pub fn multiway_equity_monitored(hands: &[HoleCards], board: &[Card], dead: &[Card], mode: EquityMode, monitor: &mut Monitor) -> Result<EquityReport, EquityError> {
+    let hands: Vec<Vec<Card>> = hands.iter().map(|hand| hand.to_vec()).collect();
+    variant_equity_monitored(&Holdem, &hands, board, dead, mode, monitor)
+}
+
+/// Check that a sampling `mode` runs at least one trial, so there is
+/// something to estimate equities from
+pub(crate) fn check_trials(mode: EquityMode) -> Result<(), EquityError> {
+    match mode {
+        EquityMode::MonteCarlo(sim) | EquityMode::Adaptive(sim, _) if sim.trials == 0 => Err(EquityError::NoTrials),
+        _ => Ok(()),
+    }
+}
+
+/// Number of trials `mode` runs when `needed` board cards are dealt from
+/// `deck_size` cards left in the deck, if it isn't stopped early
+pub(crate) fn planned_trials(deck_size: usize, needed: usize, mode: EquityMode) -> u64 {
+    match mode {
+        EquityMode::Exact => num_combinations(deck_size, needed),
+        EquityMode::MonteCarlo(sim) | EquityMode::Adaptive(sim, _) => sim.trials,
+    }
+}
+
+/// Compute every player's equity in a multiway pot when some players have
+/// folded cards nobody has seen.
+///
+/// Each entry of `folded` is the range one folded player's hand was drawn
+/// from. Their cards are kept out of the runouts, so a folding range full of
+/// aces makes aces less likely to come. Folded combos that are blocked by the
+/// hands, board or dead cards are removed, and the remaining deals are
+/// weighted by the product of the folded combos' weights.
+///
+/// With `EquityMode::Exact` every runout is enumerated for every possible
+/// deal of folded hands, which is only practical for small folding ranges.
+/// The sampling modes draw a fresh set of folded hands for every trial.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::*;
+/// use rusty_poker::range::*;
+///
+/// // Someone folded a pair of queens, taking two of the straight outs
+/// let mut folded = Range::new();
+/// folded.add([QUEEN_CLUBS, QUEEN_SPADES], 1.0);
+/// let hands = [[JACK_HEARTS, TEN_HEARTS], [ACE_CLUBS, ACE_DIAMONDS]];
+/// let board = vec![NINE_SPADES, EIGHT_DIAMONDS, TWO_CLUBS, FOUR_HEARTS];
+/// let result = multiway_equity_with_folds(&hands, &board, &[], &[folded], EquityMode::Exact).unwrap();
+/// assert_eq!(result.players[0].pwin, 6.0 / 42.0);
+/// ```
+pub fn multiway_equity_with_folds(hands: &[HoleCards], board: &[Card], dead: &[Card], folded: &[Range], mode: EquityMode) -> Result<EquityReport, EquityError> {
+    multiway_equity_with_folds_monitored(hands, board, dead, folded, mode, &mut Monitor::new())
+}
+
+/// Compute every player's equity with folded ranges as
+/// `multiway_equity_with_folds` does, reporting progress to `monitor` and
+/// stopping early if it is cancelled.
+///
+/// Exact enumeration walks the deals of folded hands one after another, so a
+/// cancelled exact calculation covers only the deals reached, the last of
+/// them cut short.
+pub fn multiway_equity_with_folds_monitored(hands: &[HoleCards], board: &[Card], dead: &[Card], folded: &[Range], mode: EquityMode, monitor: &mut Monitor) -> Result<EquityReport, EquityError> {
+    if hands.is_empty() {
+        return Err(EquityError::NoPlayers);
     }
-    for card in &board {
-        cards.remove(cards.iter().position(|x| x == card).unwrap());
+    let start = Instant::now();
+    let deck = live_deck(hands, board, dead)?;
+    check_trials(mode)?;
+    let known: Vec<Card> = hands.iter().flat_map(|hand| hand.iter()).chain(board.iter()).chain(dead.iter()).cloned().collect();
+    let folded: Vec<Range> = folded.iter().map(|range| range.without_blocked(&known)).collect();
+
+    if mode == EquityMode::Exact {
+        let mut deals: Vec<(f64, Vec<Card>)> = Vec::new();
+        for_each_fold_deal(&folded, &mut Vec::new(), 1.0, &mut |folds, weight| deals.push((weight, folds.to_vec())));
+        if deals.is_empty() {
+            return Err(EquityError::EmptyRange);
+        }
+        let runouts = num_combinations(deck.len() - 2 * folded.len(), 5 - board.len());
+        let planned = runouts * deals.len() as u64;
+        // Each player's equity over deals so far, weighted by how likely each
+        // deal of folded hands is
+        let weighted = |deals: &[(f64, &Tally)]| -> Vec<PlayerEquity> {
+            (0..hands.len()).map(|p| weighted_equity(deals.iter().map(|(weight, tally)| (*weight, tally.players[p].player_equity())))).collect()
+        };
+        let mut done: Vec<(f64, Tally)> = Vec::with_capacity(deals.len());
+        for (weight, folds) in &deals {
+            let live: Vec<Card> = deck.iter().filter(|card| !folds.contains(card)).cloned().collect();
+            let trials = done.len() as u64 * runouts;
+            let tally = walk_runouts(&live, board, mode, || Tally::new(hands.len()), |tally, full_board| tally.record(&showdown(hands, full_board)), |tally| {
+                let so_far = || weighted(&done.iter().map(|(weight, tally)| (*weight, tally)).chain(std::iter::once((*weight, tally))).collect::<Vec<_>>());
+                monitor.update(trials + tally.players[0].trials, planned, so_far)
+            });
+            done.push((*weight, tally));
+            if monitor.stopped {
+                break;
+            }
+        }
+        let mut players = weighted(&done.iter().map(|(weight, tally)| (*weight, tally)).collect::<Vec<_>>());
+        if !monitor.stopped {
+            for player in players.iter_mut() {
+                player.std_error = 0.0;
+            }
+        }
+        let counts = merged(done.into_iter().map(|(_, tally)| tally.players).collect());
+        let trials = counts[0].trials;
+        monitor.finish(trials, planned, || players.clone());
+        return Ok(EquityReport{players, counts, trials, elapsed: start.elapsed(), cancelled: monitor.stopped});
     }
 
-    let mut p1_wins = 0;
-    let mut p2_wins = 0;
-    let mut ties = 0;
-    let mut rng = thread_rng();
+    let planned = planned_trials(deck.len(), 5 - board.len(), mode);
+    let mut samplers = Vec::with_capacity(folded.len());
+    for range in &folded {
+        let weights = range.combos().iter().map(|combo| combo.weight);
+        samplers.push(WeightedIndex::new(weights).map_err(|_| EquityError::EmptyRange)?);
+    }
+    let tally = sample(mode, || FoldTally{tally: Tally::new(hands.len()), failed: false}, |rng: &mut StdRng, acc: &mut FoldTally| {
+        if acc.failed {
+            return;
+        }
+        let mut folds: Vec<Card> = Vec::with_capacity(2 * folded.len());
+        let mut rejections = 0;
+        loop {
+            folds.clear();
+            for (range, sampler) in folded.iter().zip(samplers.iter()) {
+                folds.extend_from_slice(&range.combos()[sampler.sample(rng)].cards);
+            }
+            let conflict = (0..folds.len()).any(|i| folds[i + 1..].contains(&folds[i]));
+            if !conflict {
+                break;
+            }
+            rejections += 1;
+            if rejections >= MAX_CONSECUTIVE_REJECTIONS {
+                acc.failed = true;
+                return;
+            }
+        }
+        let live: Vec<Card> = deck.iter().filter(|card| !folds.contains(card)).cloned().collect();
+        let mut full_board: Vec<Card> = board.to_vec();
+        full_board.extend(live.choose_multiple(rng, 5 - board.len()));
+        acc.tally.record(&showdown(hands, &full_board));
+    }, |acc| !acc.failed && monitor.update(acc.tally.players[0].trials, planned, || acc.tally.equities()));
+    if tally.failed {
+        return Err(EquityError::EmptyRange);
+    }
+    let (players, trials) = (tally.tally.equities(), tally.tally.players[0].trials);
+    monitor.finish(trials, planned, || players.clone());
+    Ok(EquityReport{players, counts: tally.tally.players, trials, elapsed: start.elapsed(), cancelled: false})
+}
 
-    for _ in 0..num_trials {
-        let new_cards: Vec<Card> = (&cards[..]).choose_multiple(&mut rng, 5 - board.len()).cloned().collect();
-        for card in &new_cards {
-            board.push(*card);
+/// Call `f` with every way the folded ranges can be dealt without sharing a
+/// card, passing `dead` with the folded cards added and the product of the
+/// folded combos' weights.
+fn for_each_fold_deal<F: FnMut(&[Card], f64)>(folded: &[Range], dead: &mut Vec<Card>, weight: f64, f: &mut F) {
+    match folded.split_first() {
+        None => f(dead, weight),
+        Some((range, rest)) => {
+            for combo in range.combos() {
+                if cards_overlap(&combo.cards, dead) {
+                    continue;
+                }
+                dead.extend_from_slice(&combo.cards);
+                for_each_fold_deal(rest, dead, weight * combo.weight, f);
+                dead.truncate(dead.len() - 2);
+            }
         }
-        match hand_type(h1, &board).cmp(&hand_type(h2, &board)) {
-            Ordering::Less => p2_wins +=1,
-            Ordering::Equal => ties += 1,
-            Ordering::Greater => p1_wins += 1
+    }
+}
+
+/// Running totals for `multiway_equity_with_folds`
+struct FoldTally {
+    tally: Tally,
+    /// Set when the folded hands couldn't be dealt without sharing a card
+    failed: bool,
+}
+
+impl Merge for FoldTally {
+    fn merge(&mut self, other: FoldTally) {
+        self.tally.merge(other.tally);
+        self.failed |= other.failed;
+    }
+}
+
+impl Spread for FoldTally {
+    fn max_std_error(&self) -> f64 {
+        self.tally.max_std_error()
+    }
+}
+
+/// Compute hero's equity against a weighted range.
+///
+/// Combos that share a card with hero, the board or `dead` are removed, and
+/// the dead cards are kept out of every runout. The remaining combos
+/// are weighted by their range weight. With `EquityMode::MonteCarlo` or
+/// `EquityMode::Adaptive` every combo is simulated with the given settings,
+/// so an adaptive time budget applies to each combo separately.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::*;
+/// use rusty_poker::range::*;
+///
+/// let mut villain = Range::new();
+/// villain.add([KING_SPADES, KING_CLUBS], 1.0);
+/// villain.add([ACE_HEARTS, ACE_DIAMONDS], 1.0);
+/// let board = vec![KING_HEARTS, SEVEN_DIAMONDS, TWO_CLUBS, FOUR_SPADES];
+/// let result = hand_vs_range(&[ACE_SPADES, ACE_CLUBS], &villain, &board, &[], EquityMode::Exact).unwrap();
+/// assert_eq!(result.combos.len(), 2);
+/// ```
+pub fn hand_vs_range(hero: &HoleCards, villain: &Range, board: &[Card], dead: &[Card], mode: EquityMode) -> Result<HandVsRangeEquity, EquityError> {
+    hand_vs_range_monitored(hero, villain, board, dead, mode, &mut Monitor::new())
+}
+
+/// Compute hero's equity against a weighted range as `hand_vs_range` does,
+/// reporting progress to `monitor` and stopping early if it is cancelled.
+///
+/// Progress counts the runouts shown down across every combo so far and
+/// reports hero's equity against the combos reached so far as the only
+/// player. A cancelled calculation returns the combos it reached, the last
+/// of them cut short.
+pub fn hand_vs_range_monitored(hero: &HoleCards, villain: &Range, board: &[Card], dead: &[Card], mode: EquityMode, monitor: &mut Monitor) -> Result<HandVsRangeEquity, EquityError> {
+    let start = Instant::now();
+    let deck = live_deck(&[*hero], board, dead)?;
+    check_trials(mode)?;
+    let mut known = hero.to_vec();
+    known.extend_from_slice(board);
+    known.extend_from_slice(dead);
+    let villain = villain.without_blocked(&known);
+    if villain.combos().is_empty() {
+        return Err(EquityError::EmptyRange);
+    }
+
+    let planned = planned_trials(deck.len() - 2, 5 - board.len(), mode) * villain.combos().len() as u64;
+    let mut combos: Vec<ComboEquity> = Vec::with_capacity(villain.combos().len());
+    let mut trials = 0;
+    for combo in villain.combos() {
+        let hands = [*hero, combo.cards];
+        let deck: Vec<Card> = deck.iter().filter(|card| !combo.cards.contains(card)).cloned().collect();
+        let tally = walk_runouts(&deck, board, mode, || Tally::new(2), |tally, full_board| tally.record(&showdown(&hands, full_board)), |tally| {
+            let so_far = || {
+                let reached = combos.iter().map(|combo| (combo.weight, combo.equity));
+                vec![weighted_equity(reached.chain(std::iter::once((combo.weight, tally.players[0].player_equity()))))]
+            };
+            monitor.update(trials + tally.players[0].trials, planned, so_far)
+        });
+        if tally.players[0].trials == 0 {
+            break;
+        }
+        let mut equity = tally.players[0].player_equity();
+        if mode == EquityMode::Exact && !monitor.stopped {
+            equity.std_error = 0.0;
         }
-        for card in &new_cards {
-            board.remove(board.iter().position(|x| x == card).unwrap());
+        trials += tally.players[0].trials;
+        combos.push(ComboEquity{cards: combo.cards, weight: combo.weight, equity});
+        if monitor.stopped {
+            break;
         }
     }
+    let equity = weighted_equity(combos.iter().map(|combo| (combo.weight, combo.equity)));
+    monitor.finish(trials, planned, || vec![equity]);
+    Ok(HandVsRangeEquity{equity, combos, trials, elapsed: start.elapsed(), cancelled: monitor.stopped})
+}
+
+/// Compute the exact equity of two ranges against each other on `board`.
+///
+/// Every runout is enumerated, so this is meant for flop, turn and river
+/// boards. Combos blocked by the board or `dead` are removed. Pairs of combos
+/// that share a card can't be dealt together and are skipped, and every other
+/// pair is weighted by the product of the two combo weights.
+///
+/// The work grows with the number of runouts times the number of pairs, so
+/// calculations that would take more than `MAX_EXACT_SHOWDOWNS` showdowns
+/// return `EquityError::TooManyShowdowns` instead. Sample those with
+/// `multiway_range_equity`.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::*;
+/// use rusty_poker::range::*;
+///
+/// let mut r1 = Range::new();
+/// r1.add([ACE_SPADES, ACE_CLUBS], 1.0);
+/// r1.add([QUEEN_SPADES, JACK_SPADES], 1.0);
+/// let mut r2 = Range::new();
+/// r2.add([KING_SPADES, KING_CLUBS], 1.0);
+/// let board = vec![KING_HEARTS, SEVEN_DIAMONDS, TWO_SPADES, TEN_SPADES];
+/// let result = range_vs_range(&r1, &r2, &board, &[]).unwrap();
+/// let total = result.equity[0].equity + result.equity[1].equity;
+/// assert!((total - 1.0).abs() < 1e-9);
+/// ```
+pub fn range_vs_range(r1: &Range, r2: &Range, board: &[Card], dead: &[Card]) -> Result<RangeVsRangeEquity, EquityError> {
+    range_vs_range_monitored(r1, r2, board, dead, &mut Monitor::new())
+}
 
-    [HandEquity{pwin: p1_wins as f32 / num_trials as f32, pdraw: ties as f32 / num_trials as f32},
-     HandEquity{pwin: p2_wins as f32 / num_trials as f32, pdraw: ties as f32 / num_trials as f32}]
+/// Compute the exact equity of two ranges against each other as
+/// `range_vs_range` does, reporting progress to `monitor` and stopping early
+/// if it is cancelled.
+///
+/// Runouts are walked in deck order, so a cancelled calculation covers only
+/// the runouts reached, and pairs of combos no runout was shown down for are
+/// left out.
+pub fn range_vs_range_monitored(r1: &Range, r2: &Range, board: &[Card], dead: &[Card], monitor: &mut Monitor) -> Result<RangeVsRangeEquity, EquityError> {
+    let deck = live_deck(&[], board, dead)?;
+    let known: Vec<Card> = board.iter().chain(dead.iter()).cloned().collect();
+    let ranges = [r1.without_blocked(&known), r2.without_blocked(&known)];
+    let combos: [&[WeightedCombo]; 2] = [ranges[0].combos(), ranges[1].combos()];
+    let (n1, n2) = (combos[0].len(), combos[1].len());
+    let compatible: Vec<bool> = combos[0].iter()
+        .flat_map(|c1| combos[1].iter().map(move |c2| !cards_overlap(&c1.cards, &c2.cards)))
+        .collect();
+    let pairs = compatible.iter().filter(|c| **c).count() as u64;
+    if pairs == 0 {
+        return Err(EquityError::EmptyRange);
+    }
+    let planned = num_combinations(deck.len(), 5 - board.len());
+    let showdowns = planned.saturating_mul(pairs);
+    if showdowns > MAX_EXACT_SHOWDOWNS {
+        return Err(EquityError::TooManyShowdowns(showdowns));
+    }
+
+    let mut counts = PairCounts::new(n1 * n2);
+    let mut hands: [Vec<Option<HandType>>; 2] = [vec![None; n1], vec![None; n2]];
+    let mut full_board: Vec<Card> = board.to_vec();
+    let mut runouts = 0;
+    try_for_each_combination(&deck, 5 - board.len(), |runout| {
+        full_board.truncate(board.len());
+        full_board.extend_from_slice(runout);
+        let full_board = &full_board[..];
+        for p in 0..2 {
+            for (hand, combo) in hands[p].iter_mut().zip(combos[p].iter()) {
+                *hand = if cards_overlap(&combo.cards, runout) { None } else { Some(hand_type(&combo.cards, full_board)) };
+            }
+        }
+        for (i, h1) in hands[0].iter().enumerate() {
+            let h1 = match h1 {
+                Some(h1) => h1,
+                None => continue,
+            };
+            for (j, h2) in hands[1].iter().enumerate() {
+                let k = i * n2 + j;
+                if let (true, Some(h2)) = (compatible[k], h2) {
+                    match h1.cmp(h2) {
+                        Ordering::Greater => counts.wins[0][k] += 1,
+                        Ordering::Less => counts.wins[1][k] += 1,
+                        Ordering::Equal => counts.ties[k] += 1,
+                    }
+                    counts.trials[k] += 1;
+                }
+            }
+        }
+        runouts += 1;
+        monitor.update(runouts, planned, || counts.equities(combos).0.to_vec())
+    });
+
+    monitor.finish(runouts, planned, || counts.equities(combos).0.to_vec());
+    let (equity, combos) = counts.equities(combos);
+    Ok(RangeVsRangeEquity{equity, combos, cancelled: monitor.stopped})
+}
+
+/// Showdown counts for every pair of combos in `range_vs_range`, indexed by
+/// `i * n2 + j` for the `i`th combo of the first range and the `j`th of the
+/// second
+struct PairCounts {
+    wins: [Vec<u64>; 2],
+    ties: Vec<u64>,
+    trials: Vec<u64>,
+}
+
+impl PairCounts {
+    fn new(pairs: usize) -> PairCounts {
+        PairCounts{wins: [vec![0; pairs], vec![0; pairs]], ties: vec![0; pairs], trials: vec![0; pairs]}
+    }
+
+    /// Each player's overall equity and the equity of each of their combos,
+    /// over the pairs shown down at least once
+    fn equities(&self, combos: [&[WeightedCombo]; 2]) -> ([PlayerEquity; 2], [Vec<ComboEquity>; 2]) {
+        let n2 = combos[1].len();
+        let pair_equity = |p: usize, k: usize| {
+            let n = self.trials[k] as f64;
+            let (pwin, ptie) = (self.wins[p][k] as f64 / n, self.ties[k] as f64 / n);
+            PlayerEquity{pwin, ptie, equity: pwin + ptie / 2.0, std_error: 0.0}
+        };
+        let mut result_combos: [Vec<ComboEquity>; 2] = [Vec::with_capacity(combos[0].len()), Vec::with_capacity(n2)];
+        let mut overall: [Vec<(f64, PlayerEquity)>; 2] = [Vec::new(), Vec::new()];
+        for p in 0..2 {
+            let (own, other) = (combos[p], combos[1 - p]);
+            for (i, combo) in own.iter().enumerate() {
+                let pairs: Vec<(f64, PlayerEquity)> = (0..other.len())
+                    .map(|j| if p == 0 { i * n2 + j } else { j * n2 + i })
+                    .zip(other.iter())
+                    .filter(|(k, _)| self.trials[*k] > 0)
+                    .map(|(k, opponent)| (opponent.weight, pair_equity(p, k)))
+                    .collect();
+                if pairs.is_empty() {
+                    continue;
+                }
+                // Card removal makes combos that conflict with more of the other
+                // range less likely to be dealt
+                let opponent_weight: f64 = pairs.iter().map(|(weight, _)| weight).sum();
+                let equity = weighted_equity(pairs.into_iter());
+                overall[p].push((combo.weight * opponent_weight, equity));
+                result_combos[p].push(ComboEquity{cards: combo.cards, weight: combo.weight, equity});
+            }
+        }
+        let [o1, o2] = overall;
+        ([weighted_equity(o1.into_iter()), weighted_equity(o2.into_iter())], result_combos)
+    }
+}
+
+/// How often a player finishes with each hand category, and how those hands
+/// fare at showdown.
+///
+/// Every count is a number of runouts, so `wins.count(Flush)` is the number
+/// of runouts on which the player won the whole pot with a flush.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CategoryBreakdown {
+    /// Runouts won outright, by the winning hand's category
+    pub wins: CategoryCounts,
+    /// Runouts on which the pot was split, by the player's category
+    pub ties: CategoryCounts,
+    /// Runouts lost, by the player's category
+    pub losses: CategoryCounts,
+}
+
+impl CategoryBreakdown {
+    /// Number of runouts that were shown down
+    pub fn total(&self) -> u64 {
+        self.wins.total() + self.ties.total() + self.losses.total()
+    }
+
+    /// How many times the player finished with each category, whatever the
+    /// result
+    pub fn hands(&self) -> CategoryCounts {
+        let mut hands = self.wins;
+        hands.merge(&self.ties);
+        hands.merge(&self.losses);
+        hands
+    }
+
+    /// Probability of finishing with `category`
+    pub fn frequency(&self, category: HandCategory) -> f64 {
+        self.hands().count(category) as f64 / self.total() as f64
+    }
+
+    /// Probability of winning the whole pot with `category`
+    pub fn win_frequency(&self, category: HandCategory) -> f64 {
+        self.wins.count(category) as f64 / self.total() as f64
+    }
+
+    /// Probability of splitting the pot with `category`
+    pub fn tie_frequency(&self, category: HandCategory) -> f64 {
+        self.ties.count(category) as f64 / self.total() as f64
+    }
+
+    /// Probability of losing with `category`
+    pub fn loss_frequency(&self, category: HandCategory) -> f64 {
+        self.losses.count(category) as f64 / self.total() as f64
+    }
+
+    fn record(&mut self, result: &Showdown, player: usize) {
+        let category = result.hands[player].category();
+        if !result.winners().contains(&player) {
+            self.losses.add(category);
+        } else if result.is_split() {
+            self.ties.add(category);
+        } else {
+            self.wins.add(category);
+        }
+    }
+}
+
+impl Merge for CategoryBreakdown {
+    fn merge(&mut self, other: CategoryBreakdown) {
+        self.wins.merge(&other.wins);
+        self.ties.merge(&other.ties);
+        self.losses.merge(&other.losses);
+    }
+}
+
+impl Spread for CategoryBreakdown {
+    /// Standard error of the probability of winning or splitting the pot
+    fn max_std_error(&self) -> f64 {
+        let n = self.total() as f64;
+        if n < 2.0 {
+            return 0.0;
+        }
+        let p = (self.wins.total() + self.ties.total()) as f64 / n;
+        (p * (1.0 - p) / (n - 1.0)).sqrt()
+    }
+}
+
+/// Break down how each player's hands finish on the runouts of `board`.
+///
+/// Runouts are chosen by `mode` as in `multiway_equity`, and the breakdowns
+/// are returned in the order the hands were given.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::*;
+/// use rusty_poker::hand_type::HandCategory;
+///
+/// // Top pair and a flush draw against a set on the turn
+/// let hands = [[ACE_HEARTS, KING_HEARTS], [SEVEN_CLUBS, SEVEN_DIAMONDS]];
+/// let board = vec![KING_DIAMONDS, SEVEN_HEARTS, FIVE_HEARTS, FOUR_CLUBS];
+/// let result = category_breakdown(&hands, &board, &[], EquityMode::Exact).unwrap();
+/// assert_eq!(result[0].win_frequency(HandCategory::Flush), 8.0 / 44.0);
+/// assert_eq!(result[1].wins.count(HandCategory::Trips), 27);
+/// assert_eq!(result[1].wins.count(HandCategory::FullHouse), 8);
+/// assert_eq!(result[1].wins.count(HandCategory::Quads), 1);
+/// ```
+pub fn category_breakdown(hands: &[HoleCards], board: &[Card], dead: &[Card], mode: EquityMode) -> Result<Vec<CategoryBreakdown>, EquityError> {
+    if hands.is_empty() {
+        return Err(EquityError::NoPlayers);
+    }
+    let deck = live_deck(hands, board, dead)?;
+    check_trials(mode)?;
+    Ok(walk_runouts(&deck, board, mode, || vec![CategoryBreakdown::default(); hands.len()], |breakdowns, full_board| {
+        let result = showdown(hands, full_board);
+        for (player, breakdown) in breakdowns.iter_mut().enumerate() {
+            breakdown.record(&result, player);
+        }
+    }, |_| true))
+}
+
+/// Sampled equity of three or more ranges against each other
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiwayRangeEquity {
+    /// Each player's equity against all of the other ranges
+    pub players: Vec<EquityEstimate>,
+    /// For each player, the equity of every combo in their range that isn't
+    /// blocked by the board. Combos that were never dealt have no samples.
+    pub combos: Vec<Vec<ComboEstimate>>,
+    /// Number of deals that were rejected because two players' combos shared
+    /// a card
+    pub rejected: u64,
+    /// Wall clock time spent on the calculation
+    pub elapsed: Duration,
+    /// True if the calculation was cancelled and covers only the deals made
+    /// before then
+    pub cancelled: bool,
+}
+
+/// The sampled equity of one combo of a range
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComboEstimate {
+    pub cards: HoleCards,
+    /// The combo's weight in its range
+    pub weight: f64,
+    pub estimate: EquityEstimate,
+}
+
+/// Give up on a range equity calculation after this many rejected deals in a
+/// row, since the ranges almost certainly can't be dealt together.
+const MAX_CONSECUTIVE_REJECTIONS: u32 = 100_000;
+
+/// Estimate the equity of any number of ranges against each other with
+/// `num_trials` random deals.
+///
+/// Each player's combo is drawn according to its weight, and the whole deal
+/// is thrown away and redrawn whenever two players' combos share a card. The
+/// accepted deals are therefore drawn with probability proportional to the
+/// product of the combo weights over every deal that is actually possible,
+/// which is what card removal requires. A random runout is then shown down for
+/// every accepted deal. Combos blocked by the board or `dead` are removed
+/// before any deals are drawn.
+pub fn multiway_range_equity(ranges: &[Range], board: &[Card], dead: &[Card], sim: &Simulation) -> Result<MultiwayRangeEquity, EquityError> {
+    multiway_range_equity_monitored(ranges, board, dead, sim, &mut Monitor::new())
+}
+
+/// Estimate the equity of any number of ranges against each other as
+/// `multiway_range_equity` does, reporting progress to `monitor` and stopping
+/// early if it is cancelled.
+pub fn multiway_range_equity_monitored(ranges: &[Range], board: &[Card], dead: &[Card], sim: &Simulation, monitor: &mut Monitor) -> Result<MultiwayRangeEquity, EquityError> {
+    if ranges.is_empty() {
+        return Err(EquityError::NoPlayers);
+    }
+    let start = Instant::now();
+    let deck = live_deck(&[], board, dead)?;
+    check_trials(EquityMode::MonteCarlo(*sim))?;
+    let known: Vec<Card> = board.iter().chain(dead.iter()).cloned().collect();
+    let ranges: Vec<Range> = ranges.iter().map(|range| range.without_blocked(&known)).collect();
+    let mut samplers = Vec::with_capacity(ranges.len());
+    for range in &ranges {
+        let weights = range.combos().iter().map(|combo| combo.weight);
+        samplers.push(WeightedIndex::new(weights).map_err(|_| EquityError::EmptyRange)?);
+    }
+
+    let tally = sample(EquityMode::MonteCarlo(*sim), || RangeTally::new(&ranges), |rng: &mut StdRng, tally: &mut RangeTally| {
+        if tally.failed {
+            return;
+        }
+        let mut dealt: Vec<usize> = vec![0; ranges.len()];
+        let mut hands: Vec<HoleCards> = Vec::with_capacity(ranges.len());
+        let mut rejections = 0;
+        loop {
+            hands.clear();
+            for (p, sampler) in samplers.iter().enumerate() {
+                dealt[p] = sampler.sample(rng);
+                hands.push(ranges[p].combos()[dealt[p]].cards);
+            }
+            let conflict = (0..hands.len()).any(|i| (i + 1..hands.len()).any(|j| cards_overlap(&hands[i], &hands[j])));
+            if !conflict {
+                break;
+            }
+            tally.rejected += 1;
+            rejections += 1;
+            if rejections >= MAX_CONSECUTIVE_REJECTIONS {
+                tally.failed = true;
+                return;
+            }
+        }
+
+        let live: Vec<Card> = deck.iter().filter(|card| !hands.iter().any(|hand| hand.contains(card))).cloned().collect();
+        let mut full_board: Vec<Card> = board.to_vec();
+        full_board.extend(live.choose_multiple(rng, 5 - board.len()));
+        let result = showdown(&hands, &full_board);
+        for (p, share) in result.shares().into_iter().enumerate() {
+            tally.players[p].record(share, result.is_split());
+            tally.combos[p][dealt[p]].record(share, result.is_split());
+        }
+    }, |tally| {
+        let players = || tally.players.iter().map(|stats| stats.player_equity()).collect();
+        !tally.failed && monitor.update(tally.players[0].trials, sim.trials, players)
+    });
+    if tally.failed {
+        return Err(EquityError::EmptyRange);
+    }
+    monitor.finish(tally.players[0].trials, sim.trials, || tally.players.iter().map(|stats| stats.player_equity()).collect());
+
+    let combos = ranges.iter().zip(tally.combos.iter())
+        .map(|(range, stats)| range.combos().iter().zip(stats.iter())
+            .map(|(combo, stats)| ComboEstimate{cards: combo.cards, weight: combo.weight, estimate: stats.estimate()})
+            .collect())
+        .collect();
+    Ok(MultiwayRangeEquity{
+        players: tally.players.iter().map(|stats| stats.estimate()).collect(),
+        combos,
+        rejected: tally.rejected,
+        elapsed: start.elapsed(),
+        cancelled: monitor.stopped,
+    })
+}
+
+/// One worker's running totals for `multiway_range_equity`
+struct RangeTally {
+    players: Vec<EquityCounts>,
+    combos: Vec<Vec<EquityCounts>>,
+    rejected: u64,
+    /// Set when a deal couldn't be found without conflicting combos
+    failed: bool,
+}
+
+impl RangeTally {
+    fn new(ranges: &[Range]) -> RangeTally {
+        RangeTally{
+            players: vec![EquityCounts::default(); ranges.len()],
+            combos: ranges.iter().map(|range| vec![EquityCounts::default(); range.combos().len()]).collect(),
+            rejected: 0,
+            failed: false,
+        }
+    }
+}
+
+impl Spread for RangeTally {
+    fn max_std_error(&self) -> f64 {
+        self.players.max_std_error()
+    }
+}
+
+impl Merge for RangeTally {
+    fn merge(&mut self, other: RangeTally) {
+        self.players.merge(other.players);
+        for (mine, theirs) in self.combos.iter_mut().zip(other.combos) {
+            mine.merge(theirs);
+        }
+        self.rejected += other.rejected;
+        self.failed |= other.failed;
+    }
+}
+
+/// Average equities, weighting each by its paired weight. The equities are
+/// assumed to be independent estimates when combining their standard errors.
+pub(crate) fn weighted_equity<I: Iterator<Item = (f64, PlayerEquity)>>(equities: I) -> PlayerEquity {
+    let mut total = PlayerEquity::default();
+    let mut total_weight = 0.0;
+    let mut variance = 0.0;
+    for (weight, equity) in equities {
+        total.pwin += weight * equity.pwin;
+        total.ptie += weight * equity.ptie;
+        total.equity += weight * equity.equity;
+        variance += (weight * equity.std_error).powi(2);
+        total_weight += weight;
+    }
+    PlayerEquity{
+        pwin: total.pwin / total_weight,
+        ptie: total.ptie / total_weight,
+        equity: total.equity / total_weight,
+        std_error: variance.sqrt() / total_weight,
+    }
+}
+
+/// Running totals of showdown results for each player
+struct Tally {
+    players: Vec<EquityCounts>,
+}
+
+impl Tally {
+    fn new(num_players: usize) -> Tally {
+        Tally{players: vec![EquityCounts::default(); num_players]}
+    }
+
+    fn record(&mut self, result: &Showdown) {
+        for (i, share) in result.shares().into_iter().enumerate() {
+            self.players[i].record(share, result.is_split());
+        }
+    }
+
+    fn equities(&self) -> Vec<PlayerEquity> {
+        self.players.iter().map(|stats| stats.player_equity()).collect()
+    }
+}
+
+impl Merge for Tally {
+    fn merge(&mut self, other: Tally) {
+        self.players.merge(other.players);
+    }
+}
+
+impl Spread for Tally {
+    fn max_std_error(&self) -> f64 {
+        self.players.max_std_error()
+    }
+}
+
+impl Spread for EquityCounts {
+    fn max_std_error(&self) -> f64 {
+        self.std_error()
+    }
+}
+
+/// Running totals whose sampling error can be checked by an adaptive
+/// simulation
+pub(crate) trait Spread {
+    /// The largest standard error of any player's equity
+    fn max_std_error(&self) -> f64;
+}
+
+impl<T: Spread> Spread for Vec<T> {
+    fn max_std_error(&self) -> f64 {
+        self.iter().map(|stats| stats.max_std_error()).fold(0.0, f64::max)
+    }
+}
+
+/// Complete `board` to five cards from `deck` according to `mode`, passing
+/// each full board to `f` along with an accumulator built by `init`, and
+/// return the merged accumulators.
+///
+/// `proceed` is called with the running totals after every runout when
+/// enumerating and after every batch when sampling, and the walk stops early
+/// if it returns false.
+fn walk_runouts<A, I, F, P>(deck: &[Card], board: &[Card], mode: EquityMode, init: I, f: F, proceed: P) -> A
+where
+    A: Merge + Spread + Send,
+    I: Fn() -> A + Sync,
+    F: Fn(&mut A, &[Card]) + Sync,
+    P: FnMut(&A) -> bool,
+{
+    walk_boards(&Holdem, deck, board, mode, init, f, proceed)
+}
+
+/// Complete `board` to `variant`'s board size as `walk_runouts` does for
+/// hold'em. `deck` holds the cards left to deal, as returned by
+/// `variant_live_deck`.
+pub(crate) fn walk_boards<V, A, I, F, P>(variant: &V, deck: &[Card], board: &[Card], mode: EquityMode, init: I, f: F, mut proceed: P) -> A
+where
+    V: PokerVariant,
+    A: Merge + Spread + Send,
+    I: Fn() -> A + Sync,
+    F: Fn(&mut A, &[Card]) + Sync,
+    P: FnMut(&A) -> bool,
+{
+    let needed = variant.board_size() - board.len();
+    let deal = |rng: &mut StdRng, acc: &mut A| {
+        let mut full_board: Vec<Card> = board.to_vec();
+        full_board.extend(deck.choose_multiple(rng, needed));
+        f(acc, &full_board);
+    };
+    match mode {
+        EquityMode::Exact => {
+            let mut acc = init();
+            let mut full_board: Vec<Card> = board.to_vec();
+            try_for_each_combination(deck, needed, |runout| {
+                full_board.truncate(board.len());
+                full_board.extend_from_slice(runout);
+                f(&mut acc, &full_board);
+                proceed(&acc)
+            });
+            acc
+        }
+        _ => sample(mode, init, deal, proceed),
+    }
+}
+
+/// Run `trial` as many times as the sampling `mode` calls for and return the
+/// merged accumulators.
+///
+/// Trials run in batches, and `proceed` is called with the running totals
+/// after each one. Sampling stops early if it returns false.
+///
+/// # Panics
+/// If `mode` is `EquityMode::Exact`
+pub(crate) fn sample<A, I, T, P>(mode: EquityMode, init: I, trial: T, mut proceed: P) -> A
+where
+    A: Merge + Spread + Send,
+    I: Fn() -> A + Sync,
+    T: Fn(&mut StdRng, &mut A) + Sync,
+    P: FnMut(&A) -> bool,
+{
+    match mode {
+        EquityMode::Exact => panic!("Exact calculations can't be sampled"),
+        EquityMode::MonteCarlo(sim) => {
+            let batch = sim.trials.div_ceil(PROGRESS_BATCHES).max(1000);
+            simulate_until(&sim, batch, init, trial, |acc, _| !proceed(acc))
+        }
+        EquityMode::Adaptive(sim, precision) => {
+            simulate_until(&sim, precision.batch, init, trial, |acc, elapsed| !proceed(acc) || precision.reached(acc, elapsed))
+        }
+    }
+}
+
+/// Return the hold'em deck with every hand, board card and dead card removed,
+/// or an error if any card is used twice.
+pub(crate) fn live_deck(hands: &[HoleCards], board: &[Card], dead: &[Card]) -> Result<Vec<Card>, EquityError> {
+    variant_live_deck(&Holdem, hands, board, dead)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    use super::*;
+
+    #[test]
+    fn exact_on_the_river() {
+        let board = [NINE_SPADES, EIGHT_DIAMONDS, TWO_CLUBS, FOUR_HEARTS, KING_CLUBS];
+        let counts = hand_vs_hand_exact(&[JACK_HEARTS, TEN_HEARTS], &[ACE_CLUBS, ACE_DIAMONDS], &board, &[]);
+        assert_eq!(counts, HeadsUpCounts{p1_wins: 0, p2_wins: 1, ties: 0});
+    }
+
+    #[test]
+    fn exact_on_the_turn() {
+        // The aces and queens play the first kicker and the jacks the second,
+        // and every other river chops the pot
+        let board = [KING_SPADES, KING_DIAMONDS, SEVEN_CLUBS, SEVEN_HEARTS];
+        let counts = hand_vs_hand_exact(&[ACE_SPADES, QUEEN_DIAMONDS], &[ACE_CLUBS, JACK_DIAMONDS], &board, &[]);
+        assert_eq!(counts, HeadsUpCounts{p1_wins: 5, p2_wins: 3, ties: 36});
+    }
+
+    #[test]
+    fn flop_is_enumerated_automatically() {
+        let board = [NINE_SPADES, EIGHT_DIAMONDS, TWO_CLUBS];
+        let h1 = [JACK_HEARTS, TEN_HEARTS];
+        let h2 = [ACE_CLUBS, ACE_DIAMONDS];
+        let counts = hand_vs_hand_exact(&h1, &h2, &board, &[]);
+        assert_eq!(counts.total(), 990);
+        let result = hand_vs_hand(&h1, &h2, &board, &[], 10);
+        assert_eq!(result[0].pwin, counts.p1_wins as f32 / 990.0);
+        assert_eq!(result[1].pwin, counts.p2_wins as f32 / 990.0);
+    }
+
+    #[test]
+    fn three_way_chop() {
+        // The board plays for everyone
+        let hands = [[TWO_CLUBS, THREE_CLUBS], [TWO_DIAMONDS, THREE_DIAMONDS], [TWO_HEARTS, THREE_HEARTS]];
+        let board = [ACE_SPADES, KING_SPADES, QUEEN_HEARTS, JACK_DIAMONDS, TEN_CLUBS];
+        let result = multiway_equity(&hands, &board, &[], EquityMode::Exact).unwrap();
+        assert_eq!(result.trials, 1);
+        for player in result.players {
+            assert_eq!(player.pwin, 0.0);
+            assert_eq!(player.ptie, 1.0);
+            assert!((player.equity - 1.0 / 3.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn multiway_matches_heads_up() {
+        let h1 = [JACK_HEARTS, TEN_HEARTS];
+        let h2 = [ACE_CLUBS, ACE_DIAMONDS];
+        let board = [NINE_SPADES, EIGHT_DIAMONDS, TWO_CLUBS, FOUR_HEARTS];
+        let heads_up = hand_vs_hand_exact(&h1, &h2, &board, &[]);
+        let result = multiway_equity(&[h1, h2], &board, &[], EquityMode::Exact).unwrap().players;
+        assert_eq!(result[0].pwin, heads_up.p1_wins as f64 / 44.0);
+        assert_eq!(result[1].pwin, heads_up.p2_wins as f64 / 44.0);
+        assert_eq!(result[0].std_error, 0.0);
+        // Killing a queen and a seven removes two of the straight outs
+        let result = multiway_equity(&[h1, h2], &board, &[QUEEN_CLUBS, SEVEN_CLUBS], EquityMode::Exact).unwrap().players;
+        assert_eq!(result[0].pwin, 6.0 / 42.0);
+        let sampled = multiway_equity(&[h1, h2], &board, &[], EquityMode::MonteCarlo(Simulation::new(2000))).unwrap();
+        assert_eq!(sampled.trials, 2000);
+        assert!((sampled.players[0].equity - 8.0 / 44.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn dead_cards_everywhere() {
+        // A dead queen and seven take two of the eight straight outs
+        let (h1, h2) = ([JACK_HEARTS, TEN_HEARTS], [ACE_CLUBS, ACE_DIAMONDS]);
+        let board = [NINE_SPADES, EIGHT_DIAMONDS, TWO_CLUBS, FOUR_HEARTS];
+        let dead = [QUEEN_CLUBS, SEVEN_CLUBS];
+        let counts = hand_vs_hand_exact(&h1, &h2, &board, &dead);
+        assert_eq!(counts, HeadsUpCounts{p1_wins: 6, p2_wins: 36, ties: 0});
+        assert_eq!(hand_vs_hand(&h1, &h2, &board, &dead, 10)[0].pwin, 6.0 / 42.0);
+
+        let mut villain = Range::new();
+        villain.add(h2, 1.0);
+        villain.add([QUEEN_CLUBS, QUEEN_HEARTS], 1.0);
+        let result = hand_vs_range(&h1, &villain, &board, &dead, EquityMode::Exact).unwrap();
+        assert_eq!(result.combos.len(), 1);
+        assert_eq!(result.equity.pwin, 6.0 / 42.0);
+
+        let mut hero = Range::new();
+        hero.add(h1, 1.0);
+        let result = range_vs_range(&hero, &villain, &board, &dead).unwrap();
+        assert_eq!(result.equity[0].pwin, 6.0 / 42.0);
+        assert_eq!(range_vs_range(&hero, &villain, &board, &[JACK_HEARTS]).unwrap_err(), EquityError::EmptyRange);
+
+        let result = multiway_range_equity(&[hero, villain], &board, &dead, &Simulation::new(200)).unwrap();
+        // The dead Qc blocks villain's pair of queens
+        assert_eq!(result.combos[1].len(), 1);
+        assert_eq!(multiway_range_equity(&[], &board, &[KING_CLUBS], &Simulation::new(10)).unwrap_err(), EquityError::NoPlayers);
+    }
+
+    #[test]
+    fn folded_ranges_remove_cards() {
+        let hands = [[JACK_HEARTS, TEN_HEARTS], [ACE_CLUBS, ACE_DIAMONDS]];
+        let board = [NINE_SPADES, EIGHT_DIAMONDS, TWO_CLUBS, FOUR_HEARTS];
+        // AcKc is blocked by villain's aces and dropped from the range
+        let mut folded = Range::new();
+        folded.add([QUEEN_CLUBS, SEVEN_CLUBS], 1.0);
+        folded.add([FIVE_HEARTS, SIX_HEARTS], 3.0);
+        folded.add([ACE_CLUBS, KING_CLUBS], 5.0);
+        let result = multiway_equity_with_folds(&hands, &board, &[], &[folded.clone()], EquityMode::Exact).unwrap();
+        let fold = |dead: &[Card]| multiway_equity(&hands, &board, dead, EquityMode::Exact).unwrap().players[0].equity;
+        // Folding the queen and seven costs hero two outs, the small hearts none
+        assert_eq!(fold(&[QUEEN_CLUBS, SEVEN_CLUBS]), 6.0 / 42.0);
+        assert_eq!(fold(&[FIVE_HEARTS, SIX_HEARTS]), 8.0 / 42.0);
+        let expected = (fold(&[QUEEN_CLUBS, SEVEN_CLUBS]) + 3.0 * fold(&[FIVE_HEARTS, SIX_HEARTS])) / 4.0;
+        assert!((result.players[0].equity - expected).abs() < 1e-12);
+        assert_eq!(result.trials, 84);
+
+        let sim = Simulation::new(4000).seed(9);
+        let sampled = multiway_equity_with_folds(&hands, &board, &[], &[folded], EquityMode::MonteCarlo(sim)).unwrap();
+        assert!((sampled.players[0].equity - expected).abs() < 5.0 * sampled.players[0].std_error);
+
+        // Two folded players can't both hold the only combo in their ranges
+        let mut only = Range::new();
+        only.add([TWO_SPADES, THREE_SPADES], 1.0);
+        let both = [only.clone(), only];
+        assert_eq!(multiway_equity_with_folds(&hands, &board, &[], &both, EquityMode::Exact).unwrap_err(), EquityError::EmptyRange);
+        assert_eq!(multiway_equity_with_folds(&hands, &board, &[], &both, EquityMode::MonteCarlo(Simulation::new(5))).unwrap_err(), EquityError::EmptyRange);
+    }
+
+    #[test]
+    fn rejects_duplicate_cards() {
+        let hands = [[ACE_SPADES, ACE_CLUBS], [ACE_SPADES, KING_CLUBS]];
+        assert_eq!(multiway_equity(&hands, &[], &[], EquityMode::Exact).unwrap_err(), EquityError::DuplicateCard(ACE_SPADES));
+        let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS]];
+        assert_eq!(multiway_equity(&hands, &[TWO_CLUBS], &[TWO_CLUBS], EquityMode::Exact).unwrap_err(), EquityError::DuplicateCard(TWO_CLUBS));
+        assert_eq!(multiway_equity(&[], &[], &[], EquityMode::Exact).unwrap_err(), EquityError::NoPlayers);
+    }
+
+    #[test]
+    fn rejects_zero_trials() {
+        let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS]];
+        let none = Simulation::new(0);
+        assert_eq!(multiway_equity(&hands, &[], &[], EquityMode::MonteCarlo(none)).unwrap_err(), EquityError::NoTrials);
+        assert_eq!(multiway_equity(&hands, &[], &[], EquityMode::Adaptive(none, Precision::new(0.01))).unwrap_err(), EquityError::NoTrials);
+        assert_eq!(hand_vs_hand_report(&hands[0], &hands[1], &[], &[], &none).unwrap_err(), EquityError::NoTrials);
+        // Few enough runouts to enumerate need no trials
+        let turn = [KING_HEARTS, SEVEN_DIAMONDS, TWO_CLUBS, FOUR_SPADES];
+        assert_eq!(hand_vs_hand_report(&hands[0], &hands[1], &turn, &[], &none).unwrap().trials, 44);
+        // hand_vs_hand keeps returning undefined equities rather than panicking
+        assert!(hand_vs_hand(&hands[0], &hands[1], &[], &[], 0)[0].pwin.is_nan());
+    }
+
+    #[test]
+    fn hand_vs_range_weights_combos() {
+        // Top pair against an underpair, a set and a blocked ace
+        let hero = [ACE_DIAMONDS, KING_CLUBS];
+        let board = [ACE_HEARTS, NINE_CLUBS, FOUR_DIAMONDS, TWO_SPADES];
+        let mut villain = Range::new();
+        // AdQd is blocked by hero and dropped from the range
+        villain.add([QUEEN_HEARTS, QUEEN_DIAMONDS], 3.0);
+        villain.add([ACE_DIAMONDS, QUEEN_DIAMONDS], 1.0);
+        villain.add([NINE_HEARTS, NINE_DIAMONDS], 1.0);
+        let result = hand_vs_range(&hero, &villain, &board, &[], EquityMode::Exact).unwrap();
+        assert_eq!(result.combos.len(), 2);
+        let vs_queens = result.combos[0].equity.equity;
+        let vs_set = result.combos[1].equity.equity;
+        // Only the two queens left save the underpair, while an ace on the
+        // river fills the set up
+        assert_eq!(vs_queens, 42.0 / 44.0);
+        assert_eq!(vs_set, 0.0);
+        assert!((result.equity.equity - (3.0 * vs_queens + vs_set) / 4.0).abs() < 1e-12);
+
+        let mut blocked = Range::new();
+        blocked.add([ACE_HEARTS, KING_HEARTS], 1.0);
+        assert_eq!(hand_vs_range(&hero, &blocked, &board, &[], EquityMode::Exact).unwrap_err(), EquityError::EmptyRange);
+    }
+
+    #[test]
+    fn range_vs_range_handles_card_removal() {
+        let board = [JACK_HEARTS, TEN_SPADES, FIVE_CLUBS, THREE_DIAMONDS];
+        let mut r1 = Range::new();
+        r1.add([ACE_HEARTS, QUEEN_HEARTS], 1.0);
+        r1.add([FIVE_HEARTS, FIVE_DIAMONDS], 2.0);
+        let mut r2 = Range::new();
+        r2.add([KING_SPADES, KING_DIAMONDS], 1.0);
+        r2.add([ACE_HEARTS, KING_HEARTS], 1.0);
+        let result = range_vs_range(&r1, &r2, &board, &[]).unwrap();
+
+        // AhQh can only face KK, so its equity matches the heads up result:
+        // the three aces left and the two kings for the straight
+        let ace_queen = result.combos[0][0];
+        assert_eq!(ace_queen.equity.pwin, 5.0 / 44.0);
+        let fives = result.combos[0][1].equity.equity;
+        let vs_kings = hand_vs_range(&[FIVE_HEARTS, FIVE_DIAMONDS], &Range::from_combos(vec![r2.combos()[0]]), &board, &[], EquityMode::Exact).unwrap();
+        let vs_ace_king = hand_vs_range(&[FIVE_HEARTS, FIVE_DIAMONDS], &Range::from_combos(vec![r2.combos()[1]]), &board, &[], EquityMode::Exact).unwrap();
+        assert!((fives - (vs_kings.equity.equity + vs_ace_king.equity.equity) / 2.0).abs() < 1e-12);
+
+        // Joint weights are AQ-KK: 1, 55-KK: 2 and 55-AK: 2
+        let expected = (5.0 / 44.0 + 2.0 * vs_kings.equity.equity + 2.0 * vs_ace_king.equity.equity) / 5.0;
+        assert!((result.equity[0].equity - expected).abs() < 1e-12);
+        assert!((result.equity[0].equity + result.equity[1].equity - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn range_vs_range_limits_enumeration() {
+        // Every combo of eight cards against every combo of eight others is
+        // 784 pairs, too many to enumerate preflop
+        let deck = all_cards();
+        let combos_of = |cards: &[Card]| {
+            let mut range = Range::new();
+            for_each_combination(cards, 2, |combo| range.add([combo[0], combo[1]], 1.0));
+            range
+        };
+        let (r1, r2) = (combos_of(&deck[..8]), combos_of(&deck[44..]));
+        let expected = 28 * 28 * num_combinations(52, 5);
+        assert_eq!(range_vs_range(&r1, &r2, &[], &[]).unwrap_err(), EquityError::TooManyShowdowns(expected));
+    }
+
+    #[test]
+    fn multiway_ranges() {
+        let board = [KING_SPADES, SEVEN_SPADES, TWO_HEARTS, FOUR_CLUBS];
+        let single = |cards: HoleCards| Range::from_combos(vec![WeightedCombo{cards, weight: 1.0}]);
+        let ranges = [single([ACE_SPADES, FIVE_SPADES]), single([QUEEN_HEARTS, QUEEN_DIAMONDS])];
+        let result = multiway_range_equity(&ranges, &board, &[], &Simulation::new(4000).threads(3)).unwrap();
+        let estimate = result.players[0];
+        assert_eq!(estimate.samples, 4000);
+        assert_eq!(result.rejected, 0);
+        // Fail only far outside the expected sampling error
+        let std_error = estimate.equity.std_error;
+        assert!((estimate.equity.equity - 15.0 / 44.0).abs() < 5.0 * std_error);
+        assert!(std_error > 0.0 && std_error < 0.01);
+    }
+
+    #[test]
+    fn multiway_ranges_reject_conflicting_deals() {
+        // Player 2 holds AsKs only when player 1 doesn't hold the As
+        let mut r1 = Range::new();
+        r1.add([ACE_SPADES, ACE_CLUBS], 1.0);
+        r1.add([QUEEN_HEARTS, QUEEN_DIAMONDS], 1.0);
+        let mut r2 = Range::new();
+        r2.add([ACE_SPADES, KING_SPADES], 1.0);
+        r2.add([JACK_HEARTS, JACK_DIAMONDS], 1.0);
+        let mut r3 = Range::new();
+        r3.add([TWO_CLUBS, THREE_CLUBS], 1.0);
+        let result = multiway_range_equity(&[r1, r2, r3], &[], &[], &Simulation::new(3000)).unwrap();
+        assert!(result.rejected > 0);
+        // Of the three possible deals, QQ is in two and AsAc and AsKs in one
+        let frequency = |p: usize, i: usize| result.combos[p][i].estimate.samples as f64 / 3000.0;
+        assert!((frequency(0, 0) - 1.0 / 3.0).abs() < 0.05);
+        assert!((frequency(0, 1) - 2.0 / 3.0).abs() < 0.05);
+        assert!((frequency(1, 0) - 1.0 / 3.0).abs() < 0.05);
+
+        let mut blocked = Range::new();
+        blocked.add([ACE_SPADES, ACE_CLUBS], 1.0);
+        assert_eq!(multiway_range_equity(&[blocked.clone(), blocked], &[], &[], &Simulation::new(10)).unwrap_err(), EquityError::EmptyRange);
+    }
+
+    #[test]
+    fn seeded_simulations_are_reproducible() {
+        let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS], [SEVEN_HEARTS, EIGHT_HEARTS]];
+        let mode = EquityMode::MonteCarlo(Simulation::new(3000).threads(4).seed(2021));
+        let first = multiway_equity(&hands, &[], &[], mode).unwrap().players;
+        let second = multiway_equity(&hands, &[], &[], mode).unwrap().players;
+        assert_eq!(first, second);
+        let total: f64 = first.iter().map(|player| player.equity).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn breakdown_by_category() {
+        let hands = [[ACE_HEARTS, KING_HEARTS], [SEVEN_CLUBS, SEVEN_DIAMONDS]];
+        let board = [KING_DIAMONDS, SEVEN_HEARTS, FIVE_HEARTS, FOUR_CLUBS];
+        let result = category_breakdown(&hands, &board, &[], EquityMode::Exact).unwrap();
+        let [top_pair, set] = [result[0], result[1]];
+        assert_eq!(top_pair.total(), 44);
+        // Eight of the nine hearts left win with a flush, but the 4h also
+        // fills up the set
+        assert_eq!(top_pair.wins.count(HandCategory::Flush), 8);
+        assert_eq!(top_pair.wins.total(), 8);
+        assert_eq!(top_pair.ties.total(), 0);
+        assert_eq!(top_pair.losses.count(HandCategory::Flush), 1);
+        // Aces, fives, fours and the last seven make two pair, and the kings
+        // make trips that lose to a full house
+        assert_eq!(top_pair.losses.count(HandCategory::Pair), 24);
+        assert_eq!(top_pair.losses.count(HandCategory::TwoPair), 9);
+        assert_eq!(top_pair.losses.count(HandCategory::Trips), 2);
+        assert_eq!(top_pair.hands().total(), 44);
+        assert_eq!(set.wins.total(), top_pair.losses.total());
+        assert_eq!(set.wins.count(HandCategory::Trips), 27);
+        assert_eq!(set.wins.count(HandCategory::FullHouse), 8);
+        assert_eq!(set.wins.count(HandCategory::Quads), 1);
+        assert_eq!(set.frequency(HandCategory::Trips), 35.0 / 44.0);
+        assert_eq!(set.loss_frequency(HandCategory::Trips), 8.0 / 44.0);
+
+        let sampled = category_breakdown(&hands, &[], &[], EquityMode::MonteCarlo(Simulation::new(500).threads(2))).unwrap();
+        assert_eq!(sampled[1].total(), 500);
+    }
+
+    #[test]
+    fn counts_carry_tie_shares() {
+        let hands = [[ACE_SPADES, KING_CLUBS], [ACE_CLUBS, KING_HEARTS], [SEVEN_HEARTS, SEVEN_DIAMONDS]];
+        let board = [ACE_HEARTS, KING_DIAMONDS, TWO_CLUBS, FIVE_SPADES];
+        let report = multiway_equity(&hands, &board, &[], EquityMode::Exact).unwrap();
+        let [ak1, ak2, sevens] = [report.counts[0], report.counts[1], report.counts[2]];
+        // The two sevens left give the set, and otherwise the top two pairs chop
+        assert_eq!((sevens.wins, sevens.ties, sevens.losses, sevens.trials), (2, 0, 40, 42));
+        assert_eq!((ak1.wins, ak1.ties, ak1.losses), (0, 40, 2));
+        assert_eq!(ak1, ak2);
+        assert_eq!(ak1.tie_share, 20.0);
+        assert_eq!(ak1.equity(), 20.0 / 42.0);
+        assert_eq!(ak1.player_equity().equity, report.players[0].equity);
+        assert_eq!(ak1.ptie(), 40.0 / 42.0);
+        assert_eq!(ak1.hand_equity().pdraw, ak1.ptie() as f32);
+
+        // Heads up counts convert to the same per player counts. The same two
+        // cards chop unless one of the flush draws gets there.
+        let (h1, h2) = ([ACE_SPADES, KING_SPADES], [ACE_DIAMONDS, KING_DIAMONDS]);
+        let board = [QUEEN_SPADES, JACK_DIAMONDS, TWO_CLUBS];
+        let counts = hand_vs_hand_exact(&h1, &h2, &board, &[]);
+        assert!(counts.p1_wins > 0 && counts.p2_wins > 0 && counts.ties > counts.p1_wins + counts.p2_wins);
+        let heads_up = counts.player_counts();
+        let report = multiway_equity(&[h1, h2], &board, &[], EquityMode::Exact).unwrap();
+        assert_eq!(heads_up[0], report.counts[0]);
+        assert_eq!(heads_up[1], report.counts[1]);
+        assert_eq!(report.counts[0].tie_share, counts.ties as f64 / 2.0);
+    }
+
+    #[test]
+    fn confidence_intervals() {
+        let hands = [[ACE_SPADES, FIVE_SPADES], [QUEEN_HEARTS, QUEEN_DIAMONDS]];
+        let board = [KING_SPADES, SEVEN_SPADES, TWO_HEARTS];
+        let sampled = multiway_equity(&hands, &board, &[], EquityMode::MonteCarlo(Simulation::new(4000).seed(1))).unwrap();
+        let player = sampled.players[0];
+        // Pot shares here are always 0 or 1, so the standard error is binomial
+        let binomial = (player.equity * (1.0 - player.equity) / 3999.0).sqrt();
+        assert!((player.std_error - binomial).abs() < 1e-9);
+        let (low, high) = player.confidence_interval();
+        assert!((high - player.equity - CONFIDENCE_Z * player.std_error).abs() < 1e-12);
+        assert!((player.equity - low - CONFIDENCE_Z * player.std_error).abs() < 1e-12);
+
+        let exact = hand_vs_hand_report(&hands[0], &hands[1], &board, &[], &Simulation::new(10)).unwrap();
+        assert_eq!(exact.players[0].std_error, 0.0);
+        assert_eq!(exact.trials, 990);
+        let preflop = hand_vs_hand_report(&hands[0], &hands[1], &[], &[], &Simulation::new(1000).threads(2).seed(4)).unwrap();
+        assert!(preflop.players[0].std_error > 0.0);
+        assert_eq!(preflop.counts[0].hand_equity().pwin, preflop.players[0].pwin as f32);
+    }
+
+    #[test]
+    fn adaptive_sampling_stops_at_target_precision() {
+        let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS]];
+        let sim = Simulation::new(1_000_000).threads(2).seed(5);
+        let result = multiway_equity(&hands, &[], &[], EquityMode::Adaptive(sim, Precision::new(0.02).batch(500))).unwrap();
+        assert!(CONFIDENCE_Z * result.players[0].std_error <= 0.02);
+        // Equity near 0.82 needs around 1,400 samples for a 2% half width
+        assert!(result.trials < 5000);
+        assert_eq!(result.trials % 500, 0);
+
+        // A time budget that has already run out stops after the first batch
+        let precision = Precision::new(0.0).batch(300).time_budget(Duration::from_secs(0));
+        let result = multiway_equity(&hands, &[], &[], EquityMode::Adaptive(sim, precision)).unwrap();
+        assert_eq!(result.trials, 300);
+
+        // Without a time budget an unreachable target runs every trial
+        let precision = Precision::new(0.0).batch(300);
+        let result = multiway_equity(&hands, &[], &[], EquityMode::Adaptive(Simulation::new(1000), precision)).unwrap();
+        assert_eq!(result.trials, 1000);
+    }
+
+    #[test]
+    fn progress_is_reported_until_cancelled() {
+        let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS]];
+        let mut seen = Vec::new();
+        let mut monitor = Monitor::new().on_progress(|progress| seen.push((progress.trials, progress.planned)));
+        let sim = Simulation::new(5000).seed(3);
+        let result = multiway_equity_monitored(&hands, &[], &[], EquityMode::MonteCarlo(sim), &mut monitor).unwrap();
+        drop(monitor);
+        assert!(!result.cancelled);
+        assert_eq!(result.trials, 5000);
+        assert_eq!(seen, vec![(1000, 5000), (2000, 5000), (3000, 5000), (4000, 5000), (5000, 5000)]);
+
+        // A token cancelled up front still returns the first batch
+        let token = CancelToken::new();
+        token.cancel();
+        let mut monitor = Monitor::new().cancel_token(token.clone());
+        let result = multiway_equity_monitored(&hands, &[], &[], EquityMode::MonteCarlo(sim), &mut monitor).unwrap();
+        assert!(result.cancelled);
+        assert_eq!(result.trials, 1000);
+        assert!(result.players[0].std_error > 0.0);
+
+        // Exact enumeration stops at the first progress report once a callback
+        // cancels it, 152 of the 15,180 runouts in
+        let board = [KING_HEARTS, SEVEN_DIAMONDS];
+        let stopper = CancelToken::new();
+        let handle = stopper.clone();
+        let mut monitor = Monitor::new().cancel_token(stopper).on_progress(|_| handle.cancel());
+        let result = multiway_equity_monitored(&hands, &board, &[], EquityMode::Exact, &mut monitor).unwrap();
+        assert!(result.cancelled);
+        assert_eq!(result.trials, 152);
+        assert!(result.players[0].std_error > 0.0);
+
+        let (mut aces, mut kings) = (Range::new(), Range::new());
+        aces.add([ACE_SPADES, ACE_CLUBS], 1.0);
+        kings.add([KING_SPADES, KING_CLUBS], 1.0);
+        let ranges = [aces, kings];
+        let mut monitor = Monitor::new().cancel_token(token);
+        let result = multiway_range_equity_monitored(&ranges, &[], &[], &sim, &mut monitor).unwrap();
+        assert!(result.cancelled);
+    }
+
+    #[test]
+    fn range_calculations_report_progress() {
+        let mut villain = Range::new();
+        villain.add([KING_SPADES, KING_CLUBS], 1.0);
+        villain.add([QUEEN_HEARTS, JACK_HEARTS], 1.0);
+        let mut seen = Vec::new();
+        let mut monitor = Monitor::new().on_progress(|progress| seen.push((progress.trials, progress.planned, progress.players.len())));
+        let sim = Simulation::new(2000).seed(9);
+        let result = hand_vs_range_monitored(&[ACE_SPADES, ACE_CLUBS], &villain, &[], &[], EquityMode::MonteCarlo(sim), &mut monitor).unwrap();
+        drop(monitor);
+        assert!(!result.cancelled);
+        assert_eq!(seen, vec![(1000, 4000, 1), (2000, 4000, 1), (3000, 4000, 1), (4000, 4000, 1)]);
+
+        // Cancelling stops partway through the first combo
+        let token = CancelToken::new();
+        token.cancel();
+        let mut monitor = Monitor::new().cancel_token(token.clone());
+        let result = hand_vs_range_monitored(&[ACE_SPADES, ACE_CLUBS], &villain, &[], &[], EquityMode::MonteCarlo(sim), &mut monitor).unwrap();
+        assert!(result.cancelled);
+        assert_eq!(result.combos.len(), 1);
+        assert_eq!(result.trials, 1000);
+        assert!(result.equity.equity > 0.7);
+
+        // Enumerating a flop is cancelled from the first progress report, after
+        // 12 of the 1,176 runouts
+        let mut hero = Range::new();
+        hero.add([ACE_SPADES, ACE_CLUBS], 1.0);
+        let board = [KING_HEARTS, SEVEN_DIAMONDS, TWO_CLUBS];
+        let stopper = CancelToken::new();
+        let handle = stopper.clone();
+        let mut seen = Vec::new();
+        let mut monitor = Monitor::new().cancel_token(stopper).on_progress(|progress| {
+            seen.push((progress.trials, progress.planned));
+            handle.cancel();
+        });
+        let result = range_vs_range_monitored(&hero, &villain, &board, &[], &mut monitor).unwrap();
+        drop(monitor);
+        assert!(result.cancelled);
+        assert_eq!(seen, vec![(12, 1176)]);
+        assert!((result.equity[0].equity + result.equity[1].equity - 1.0).abs() < 1e-9);
+        assert!(!range_vs_range(&hero, &villain, &board, &[]).unwrap().cancelled);
+
+        // Progress counts runouts across every combo, ending with a final
+        // report once every runout has been shown down
+        let board = [KING_HEARTS, SEVEN_DIAMONDS, TWO_CLUBS, FOUR_SPADES];
+        let mut seen = Vec::new();
+        let mut monitor = Monitor::new().on_progress(|progress| seen.push(progress.trials));
+        let result = hand_vs_range_monitored(&[ACE_SPADES, ACE_CLUBS], &villain, &board, &[], EquityMode::Exact, &mut monitor).unwrap();
+        drop(monitor);
+        assert_eq!(result.trials, 88);
+        assert_eq!(seen, (1..=88).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn heads_up_and_folded_calculations_report_progress() {
+        let hands = [[ACE_SPADES, FIVE_SPADES], [QUEEN_HEARTS, QUEEN_DIAMONDS]];
+        let board = [KING_SPADES, SEVEN_SPADES, TWO_HEARTS];
+        let mut seen = Vec::new();
+        let mut monitor = Monitor::new().on_progress(|progress| seen.push((progress.trials, progress.planned)));
+        let sim = Simulation::new(2500).seed(2);
+        let result = hand_vs_hand_report_monitored(&hands[0], &hands[1], &[], &[], &sim, &mut monitor).unwrap();
+        drop(monitor);
+        assert!(!result.cancelled);
+        // The last batch is short, and still reported at the end
+        assert_eq!(seen, vec![(1000, 2500), (2000, 2500), (2500, 2500)]);
+
+        let mut folded = Range::new();
+        folded.add([TWO_SPADES, THREE_SPADES], 1.0);
+        folded.add([NINE_CLUBS, EIGHT_CLUBS], 1.0);
+        let stopper = CancelToken::new();
+        let handle = stopper.clone();
+        let mut monitor = Monitor::new().cancel_token(stopper).on_progress(|progress| {
+            if progress.trials > 903 {
+                handle.cancel();
+            }
+        });
+        let result = multiway_equity_with_folds_monitored(&hands, &board, &[], &[folded.clone()], EquityMode::Exact, &mut monitor).unwrap();
+        drop(monitor);
+        // Each deal of the folded hand leaves 903 runouts, and the second deal
+        // is cut short
+        assert!(result.cancelled);
+        assert!(result.trials > 903 && result.trials < 1806);
+        let full = multiway_equity_with_folds(&hands, &board, &[], &[folded], EquityMode::Exact).unwrap();
+        assert_eq!(full.trials, 1806);
+        assert!(!full.cancelled);
+    }
 }
@@ -0,0 +1,130 @@
+//! Count how often each hand category occurs across every possible hand
+
+use crate::card::*;
+use crate::hand_type::*;
+use std::thread;
+
+/// Number of hands that fall into each `HandCategory`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CategoryCounts {
+    counts: [u64; 9],
+}
+
+impl CategoryCounts {
+    /// Number of hands in `category`
+    pub fn count(&self, category: HandCategory) -> u64 {
+        self.counts[category as usize]
+    }
+
+    /// Total number of hands counted
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Fraction of all counted hands that are in `category`
+    pub fn frequency(&self, category: HandCategory) -> f64 {
+        self.count(category) as f64 / self.total() as f64
+    }
+
+    pub(crate) fn add(&mut self, category: HandCategory) {
+        self.counts[category as usize] += 1;
+    }
+
+    pub(crate) fn merge(&mut self, other: &CategoryCounts) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other;
+        }
+    }
+}
+
+/// Count the category of each of the 2,598,960 five card hands
+///
+/// # Example
+/// ```no_run
+/// use rusty_poker::frequency::*;
+/// use rusty_poker::hand_type::HandCategory;
+///
+/// let counts = five_card_frequencies();
+/// assert_eq!(counts.total(), 2_598_960);
+/// assert_eq!(counts.count(HandCategory::Quads), 624);
+/// ```
+pub fn five_card_frequencies() -> CategoryCounts {
+    enumerate_frequencies(5)
+}
+
+/// Count the best five card category of each of the 133,784,560 seven card
+/// hands
+pub fn seven_card_frequencies() -> CategoryCounts {
+    enumerate_frequencies(7)
+}
+
+/// Evaluate every `n` card hand, splitting the work by the lowest indexed
+/// card so each thread walks an independent slice of the hands.
+fn enumerate_frequencies(n: usize) -> CategoryCounts {
+    let cards = all_cards();
+    let cards = &cards;
+    let mut counts = CategoryCounts::default();
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..=cards.len() - n)
+            .map(|first| scope.spawn(move || {
+                let mut counts = CategoryCounts::default();
+                let mut hand: Vec<Card> = vec![cards[first]];
+                count_hands(&cards[first + 1..], n - 1, &mut hand, &mut counts);
+                counts
+            }))
+            .collect();
+        for worker in workers {
+            counts.merge(&worker.join().unwrap());
+        }
+    });
+    counts
+}
+
+fn count_hands(cards: &[Card], remaining: usize, hand: &mut Vec<Card>, counts: &mut CategoryCounts) {
+    if remaining == 0 {
+        counts.add(hand_type(&[hand[0], hand[1]], &hand[2..]).category());
+        return;
+    }
+    for i in 0..=cards.len() - remaining {
+        hand.push(cards[i]);
+        count_hands(&cards[i + 1..], remaining - 1, hand, counts);
+        hand.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hand_type::HandCategory::*;
+    use super::*;
+
+    #[test]
+    fn five_card_counts() {
+        let counts = five_card_frequencies();
+        assert_eq!(counts.total(), 2_598_960);
+        assert_eq!(counts.count(StraightFlush), 40);
+        assert_eq!(counts.count(Quads), 624);
+        assert_eq!(counts.count(FullHouse), 3_744);
+        assert_eq!(counts.count(Flush), 5_108);
+        assert_eq!(counts.count(Straight), 10_200);
+        assert_eq!(counts.count(Trips), 54_912);
+        assert_eq!(counts.count(TwoPair), 123_552);
+        assert_eq!(counts.count(Pair), 1_098_240);
+        assert_eq!(counts.count(HighCard), 1_302_540);
+    }
+
+    #[test]
+    #[ignore = "evaluates all 133 million seven card hands"]
+    fn seven_card_counts() {
+        let counts = seven_card_frequencies();
+        assert_eq!(counts.total(), 133_784_560);
+        assert_eq!(counts.count(StraightFlush), 41_584);
+        assert_eq!(counts.count(Quads), 224_848);
+        assert_eq!(counts.count(FullHouse), 3_473_184);
+        assert_eq!(counts.count(Flush), 4_047_644);
+        assert_eq!(counts.count(Straight), 6_180_020);
+        assert_eq!(counts.count(Trips), 6_461_620);
+        assert_eq!(counts.count(TwoPair), 31_433_400);
+        assert_eq!(counts.count(Pair), 58_627_800);
+        assert_eq!(counts.count(HighCard), 23_294_460);
+    }
+}
@@ -1,9 +1,9 @@
-/// This module is responsible for determining hand type
+//! This module is responsible for determining hand type
 
 use crate::card::*;
 use crate::util::*;
 
-#[derive(Eq, PartialEq, Debug, PartialOrd, Ord)]
+#[derive(Eq, PartialEq, Debug, PartialOrd, Ord, Clone, Copy)]
 pub enum HandType {
     /// Ranks, sorted in descending order, of a high card hand
     HighCard([Card; 5]),
@@ -25,6 +25,45 @@ pub enum HandType {
     StraightFlush([Card; 5])
 }
 
+/// The category of a hand, ignoring the cards that make it up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HandCategory {
+    HighCard,
+    Pair,
+    TwoPair,
+    Trips,
+    Straight,
+    Flush,
+    FullHouse,
+    Quads,
+    StraightFlush,
+}
+
+impl HandCategory {
+    /// Return every category, from weakest to strongest
+    pub fn all() -> [HandCategory; 9] {
+        [HandCategory::HighCard, HandCategory::Pair, HandCategory::TwoPair, HandCategory::Trips,
+         HandCategory::Straight, HandCategory::Flush, HandCategory::FullHouse, HandCategory::Quads,
+         HandCategory::StraightFlush]
+    }
+}
+
+impl HandType {
+    /// Return the category of this hand
+    pub fn category(&self) -> HandCategory {
+        match self {
+            HandType::HighCard(..) => HandCategory::HighCard,
+            HandType::Pair(..) => HandCategory::Pair,
+            HandType::TwoPair(..) => HandCategory::TwoPair,
+            HandType::Trips(..) => HandCategory::Trips,
+            HandType::Straight(..) => HandCategory::Straight,
+            HandType::Flush(..) => HandCategory::Flush,
+            HandType::FullHouse(..) => HandCategory::FullHouse,
+            HandType::Quads(..) => HandCategory::Quads,
+            HandType::StraightFlush(..) => HandCategory::StraightFlush,
+        }
+    }
+}
 
 /// Group the cards by suit, ordered by rank.
 ///
@@ -90,7 +129,7 @@ pub fn group_by_rank_freq(cards: &Vec<Card>) -> [Vec<Vec<Card>>; 5] {
     }
     for cv in grouped_by_rank.iter().rev() {
         assert!(cv.len() < 5);
-        grouped_by_rank_freq[cv.len() as usize].push(cv.to_vec());
+        grouped_by_rank_freq[cv.len()].push(cv.to_vec());
     }
     grouped_by_rank_freq
 }
@@ -137,8 +176,8 @@ pub fn get_straight(cards: &Vec<Card>) -> Option<HandType> {
             }
         }
         // Now, test for ace-low straight
-        if last_rank == Rank::Two && straight.len() ==  4 && cards.get(0).unwrap().rank == Rank::Ace {
-            straight.push(*cards.get(0).unwrap());
+        if last_rank == Rank::Two && straight.len() ==  4 && cards[0].rank == Rank::Ace {
+            straight.push(cards[0]);
             return Some(HandType::Straight(card_vec_to_card_array(&straight).unwrap()));
         }
     }
@@ -164,12 +203,11 @@ pub fn get_straight(cards: &Vec<Card>) -> Option<HandType> {
 /// assert_eq!(no_straight_flush, None);
 /// ```
 pub fn get_straight_flush(cards: &Vec<Card>) -> Option<HandType> {
-    let by_suit = group_by_suit(&cards);
+    let by_suit = group_by_suit(cards);
     for suit in all_suits().iter() {
         let suited_cards = &by_suit[*suit as usize];
-        match get_straight(suited_cards) {
-            Some(HandType::Straight(cards)) => return Some(HandType::StraightFlush(cards)),
-            _ => (),
+        if let Some(HandType::Straight(cards)) = get_straight(suited_cards) {
+            return Some(HandType::StraightFlush(cards));
         }
     }
     None
@@ -193,12 +231,11 @@ pub fn get_straight_flush(cards: &Vec<Card>) -> Option<HandType> {
 /// assert_eq!(flush, Some(HandType::Flush([ACE_CLUBS, KING_CLUBS, QUEEN_CLUBS, TEN_CLUBS, NINE_CLUBS])));
 /// ```
 pub fn get_flush(cards: &Vec<Card>) -> Option<HandType> {
-    let by_suit = group_by_suit(&cards);
+    let by_suit = group_by_suit(cards);
     for suit in all_suits().iter() {
         let suited_cards = &by_suit[*suit as usize];
-        match card_vec_to_card_array(suited_cards) {
-            Some(arr) => return Some(HandType::Flush(arr)),
-            _ => (),
+        if let Some(arr) = card_vec_to_card_array(suited_cards) {
+            return Some(HandType::Flush(arr));
         }
     }
     None
@@ -223,10 +260,10 @@ pub fn get_flush(cards: &Vec<Card>) -> Option<HandType> {
 /// ```
 pub fn get_quads(cards: &Vec<Card>) -> Option<HandType> {
     let grouped_by_rank = group_by_rank_freq(cards);
-    let quads: [Card; 4] = match grouped_by_rank[4].get(0) {
+    let quads: [Card; 4] = match grouped_by_rank[4].first() {
         Some(vec) => {
             assert!(vec.len() == 4);
-            [vec.get(0).unwrap().clone(), vec.get(1).unwrap().clone(), vec.get(2).unwrap().clone(), vec.get(3).unwrap().clone()]
+            [vec[0], vec[1], vec[2], vec[3]]
         }
         None => return None
     };
@@ -261,22 +298,22 @@ pub fn get_quads(cards: &Vec<Card>) -> Option<HandType> {
 /// ```
 pub fn get_full_house(cards: &Vec<Card>) -> Option<HandType> {
     let grouped_by_rank = group_by_rank_freq(cards);
-    let trips: [Card; 3] = match grouped_by_rank[3].get(0) {
+    let trips: [Card; 3] = match grouped_by_rank[3].first() {
         Some(vec) => {
             assert!(vec.len() == 3);
-            [vec.get(0).unwrap().clone(), vec.get(1).unwrap().clone(), vec.get(2).unwrap().clone()]
+            [vec[0], vec[1], vec[2]]
         }
         None => return None
     };
-    let pair: [Card; 2] = match grouped_by_rank[2].get(0) {
+    let pair: [Card; 2] = match grouped_by_rank[2].first() {
         Some(vec) => {
             assert!(vec.len() == 2);
-            [vec.get(0).unwrap().clone(), vec.get(1).unwrap().clone()]
+            [vec[0], vec[1]]
         }
         None => match grouped_by_rank[3].get(1) {
             Some(vec) => {
                 assert!(vec.len() == 3);
-                [vec.get(0).unwrap().clone(), vec.get(1).unwrap().clone()]
+                [vec[0], vec[1]]
             }
             None => return None
         }
@@ -317,31 +354,28 @@ pub fn get_trips_or_pairs(cards: &Vec<Card>) -> Option<HandType> {
     let grouped_by_rank = group_by_rank_freq(cards);
     let trips = &grouped_by_rank[3];
 
-    match trips.get(0) {
-        Some(vec) => {
-            assert!(vec.len() == 3);
-            let trips = [vec.get(0).unwrap().clone(), vec.get(1).unwrap().clone(), vec.get(2).unwrap().clone()];
-            let trips_rank = trips[0].rank;
-            let mut kickers = Vec::new();
-            for card in cards {
-                if card.rank != trips_rank {
-                    kickers.push(*card);
-                }
-                if kickers.len() >= 2 {
+    if let Some(vec) = trips.first() {
+        assert!(vec.len() == 3);
+        let trips = [vec[0], vec[1], vec[2]];
+        let trips_rank = trips[0].rank;
+        let mut kickers = Vec::new();
+        for card in cards {
+            if card.rank != trips_rank {
+                kickers.push(*card);
+            }
+            if kickers.len() >= 2 {
 
-                    return Some(HandType::Trips(trips, [kickers.get(0).unwrap().clone(), kickers.get(1).unwrap().clone()]))
-                }
+                return Some(HandType::Trips(trips, [kickers[0], kickers[1]]))
             }
         }
-        None => ()
     }
     let pairs = &grouped_by_rank[2];
     if pairs.len() >= 2 {
-        let p1 = pairs.get(0).unwrap();
-        let p1 = [p1.get(0).unwrap().clone(), p1.get(1).unwrap().clone()];
+        let p1 = &pairs[0];
+        let p1 = [p1[0], p1[1]];
         let p1_rank = p1[0].rank;
-        let p2 = pairs.get(1).unwrap();
-        let p2 = [p2.get(0).unwrap().clone(), p2.get(1).unwrap().clone()];
+        let p2 = &pairs[1];
+        let p2 = [p2[0], p2[1]];
         let p2_rank = p2[0].rank;
 
         for card in cards {
@@ -352,15 +386,15 @@ pub fn get_trips_or_pairs(cards: &Vec<Card>) -> Option<HandType> {
         panic!("Illegal State: found two pair ith no kicker");
     }
     if pairs.len() == 1 {
-        let p = pairs.get(0).unwrap();
-        let p = [p.get(0).unwrap().clone(), p.get(1).unwrap().clone()];
+        let p = &pairs[0];
+        let p = [p[0], p[1]];
         let p_rank = p[0].rank;
         let mut kickers = Vec::new();
         for card in cards {
             if card.rank != p_rank {
                 kickers.push(*card);
                 if kickers.len() == 3 {
-                    return Some(HandType::Pair(p, [kickers.get(0).unwrap().clone(), kickers.get(1).unwrap().clone(), kickers.get(2).unwrap().clone()]))
+                    return Some(HandType::Pair(p, [kickers[0], kickers[1], kickers[2]]))
                 }
             }
         }
@@ -369,7 +403,7 @@ pub fn get_trips_or_pairs(cards: &Vec<Card>) -> Option<HandType> {
     None
 }
 
-pub fn get_high_card(cards: &Vec<Card>) -> Option<HandType> {
+pub fn get_high_card(cards: &[Card]) -> Option<HandType> {
     Some(HandType::HighCard(card_vec_to_card_array(cards).unwrap()))
 }
 
@@ -386,19 +420,19 @@ pub fn get_high_card(cards: &Vec<Card>) -> Option<HandType> {
 /// let pair = hand_type(&[ACE_SPADES, ACE_CLUBS], &vec![KING_DIAMONDS, NINE_HEARTS, EIGHT_CLUBS, FIVE_CLUBS, FOUR_CLUBS]);
 /// assert_eq!(pair, HandType::Pair([ACE_SPADES, ACE_CLUBS], [KING_DIAMONDS, NINE_HEARTS, EIGHT_CLUBS]));
 /// ```
-pub fn hand_type(hand: &HoleCards, board: &Vec<Card>) -> HandType {
+pub fn hand_type(hand: &HoleCards, board: &[Card]) -> HandType {
     let mut cards = Vec::new();
     cards.extend_from_slice(hand);
     cards.extend_from_slice(board);
     cards.sort_by(|a, b| b.cmp(a));
 
-    return get_straight_flush(&cards)
+    get_straight_flush(&cards)
         .or_else(|| get_quads(&cards))
         .or_else(|| get_full_house(&cards))
         .or_else(|| get_flush(&cards))
         .or_else(|| get_straight(&cards))
         .or_else(|| get_trips_or_pairs(&cards))
-        .or_else(|| get_high_card(&cards)).unwrap();
+        .or_else(|| get_high_card(&cards)).unwrap()
 }
 
 #[cfg(test)]
@@ -462,7 +496,15 @@ mod tests {
 
     #[test]
     fn two_pair() {
-
+        assert_eq!(get_trips_or_pairs(&vec![ACE_DIAMONDS, ACE_HEARTS, KING_CLUBS, FIVE_DIAMONDS, FIVE_HEARTS, FOUR_CLUBS, TWO_SPADES]),
+                   Some(HandType::TwoPair([ACE_DIAMONDS, ACE_HEARTS], [FIVE_DIAMONDS, FIVE_HEARTS], KING_CLUBS)));
+        // With three pairs the third pair can play as the kicker
+        assert_eq!(get_trips_or_pairs(&vec![KING_DIAMONDS, KING_SPADES, NINE_HEARTS, NINE_SPADES, EIGHT_CLUBS, EIGHT_HEARTS, FOUR_DIAMONDS]),
+                   Some(HandType::TwoPair([KING_DIAMONDS, KING_SPADES], [NINE_HEARTS, NINE_SPADES], EIGHT_CLUBS)));
+        assert_eq!(hand_type(&[JACK_CLUBS, SEVEN_HEARTS], &[JACK_SPADES, SEVEN_CLUBS, THREE_DIAMONDS]).category(), HandCategory::TwoPair);
+        assert!(hand_type(&[ACE_CLUBS, SEVEN_HEARTS], &[ACE_SPADES, SEVEN_CLUBS, TWO_DIAMONDS, TWO_CLUBS, KING_HEARTS])
+                > hand_type(&[ACE_HEARTS, SIX_HEARTS], &[ACE_SPADES, SEVEN_CLUBS, TWO_DIAMONDS, TWO_CLUBS, KING_HEARTS]));
+        assert_eq!(get_trips_or_pairs(&vec![ACE_DIAMONDS, KING_HEARTS, QUEEN_CLUBS, FIVE_DIAMONDS, FIVE_HEARTS, FOUR_CLUBS, TWO_SPADES]).unwrap().category(),
+                   HandCategory::Pair);
     }
-
 }
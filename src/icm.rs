@@ -0,0 +1,349 @@
+//! Tournament equity with the Independent Chip Model
+
+use crate::decision::Spot;
+use crate::equity::PlayerEquity;
+use crate::simulation::*;
+use rand::Rng;
+
+/// Largest field `IcmMode::Exact` accepts. The recursion visits every subset
+/// of the players who finish in the money, so its cost doubles with each
+/// player.
+pub const MAX_EXACT_PLAYERS: usize = 20;
+
+/// How the finishing orders of an ICM calculation are weighed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IcmMode {
+    /// Follow every finishing order with the Malmuth-Harville recursion
+    Exact,
+    /// Draw random finishing orders as configured by the simulation, for
+    /// fields too large to follow exactly
+    Sampled(Simulation),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IcmError {
+    /// No stacks were given
+    NoPlayers,
+    /// A stack is negative or not a number
+    InvalidStack(f64),
+    /// Every stack is empty
+    NoChips,
+    /// Too many players for `IcmMode::Exact`
+    TooManyPlayers(usize),
+    /// The all in players are the same player or not in the field
+    InvalidPlayers(usize, usize),
+}
+
+/// Compute each player's expected prize, or $EV, from their chip stack.
+///
+/// The Independent Chip Model, as described by Malmuth and Harville, has each
+/// player finish first with probability proportional to their stack, and
+/// each later place go to one of the players left in proportion to theirs.
+/// `payouts[i]` is the prize for finishing in place `i`, counting from first.
+/// Players with empty stacks have already busted and share the prizes for
+/// the bottom places equally.
+///
+/// # Example
+/// ```
+/// use rusty_poker::icm::*;
+///
+/// let ev = icm_equity(&[50.0, 30.0, 20.0], &[50.0, 30.0, 20.0], IcmMode::Exact).unwrap();
+/// // The chip leader is worth less than half the prize pool
+/// assert!((ev[0] - 38.392857).abs() < 1e-6);
+/// assert!((ev.iter().sum::<f64>() - 100.0).abs() < 1e-9);
+/// ```
+pub fn icm_equity(stacks: &[f64], payouts: &[f64], mode: IcmMode) -> Result<Vec<f64>, IcmError> {
+    if stacks.is_empty() {
+        return Err(IcmError::NoPlayers);
+    }
+    if let Some(&stack) = stacks.iter().find(|stack| stack.is_nan() || **stack < 0.0) {
+        return Err(IcmError::InvalidStack(stack));
+    }
+    let live: Vec<usize> = (0..stacks.len()).filter(|&p| stacks[p] > 0.0).collect();
+    if live.is_empty() {
+        return Err(IcmError::NoChips);
+    }
+    let prize = |place: usize| payouts.get(place).copied().unwrap_or(0.0);
+
+    let live_stacks: Vec<f64> = live.iter().map(|&p| stacks[p]).collect();
+    let paid = payouts.len().min(live.len());
+    let live_ev = match mode {
+        IcmMode::Exact if live.len() > MAX_EXACT_PLAYERS => return Err(IcmError::TooManyPlayers(live.len())),
+        IcmMode::Exact => harville(&live_stacks, paid, prize),
+        IcmMode::Sampled(sim) => sampled_harville(&live_stacks, paid, prize, &sim),
+    };
+
+    // Busted players split the prizes for the places below every live player
+    let busted = stacks.len() - live.len();
+    let busted_ev = (live.len()..stacks.len()).map(prize).sum::<f64>() / busted.max(1) as f64;
+    let mut ev = vec![busted_ev; stacks.len()];
+    for (&p, value) in live.iter().zip(live_ev) {
+        ev[p] = value;
+    }
+    Ok(ev)
+}
+
+/// Follow every finishing order through the first `paid` places. `placed[mask]`
+/// is the probability that the players in `mask` take the first
+/// `mask.count_ones()` places in some order.
+fn harville<P: Fn(usize) -> f64>(stacks: &[f64], paid: usize, prize: P) -> Vec<f64> {
+    let n = stacks.len();
+    let total: f64 = stacks.iter().sum();
+    let mut placed = vec![0.0; 1 << n];
+    let mut ev = vec![0.0; n];
+    placed[0] = 1.0;
+    for mask in 0usize..1 << n {
+        let place = mask.count_ones() as usize;
+        if place >= paid || placed[mask] == 0.0 {
+            continue;
+        }
+        let left = total - (0..n).filter(|p| mask & 1 << p != 0).map(|p| stacks[p]).sum::<f64>();
+        for p in (0..n).filter(|p| mask & 1 << p == 0) {
+            let next = placed[mask] * stacks[p] / left;
+            ev[p] += next * prize(place);
+            placed[mask | 1 << p] += next;
+        }
+    }
+    ev
+}
+
+/// Running prize totals for sampled finishing orders
+struct IcmTally {
+    totals: Vec<f64>,
+}
+
+impl Merge for IcmTally {
+    fn merge(&mut self, other: IcmTally) {
+        for (mine, theirs) in self.totals.iter_mut().zip(other.totals) {
+            *mine += theirs;
+        }
+    }
+}
+
+/// Estimate the same prizes as `harville` from random finishing orders.
+///
+/// Giving each player an exponential finishing time with rate equal to their
+/// stack and ordering the players by it draws orders with exactly the
+/// Malmuth-Harville probabilities.
+fn sampled_harville<P: Fn(usize) -> f64 + Sync>(stacks: &[f64], paid: usize, prize: P, sim: &Simulation) -> Vec<f64> {
+    let tally = merged(simulate(sim, || IcmTally{totals: vec![0.0; stacks.len()]}, |rng, tally| {
+        let mut order: Vec<(f64, usize)> = stacks.iter().enumerate().map(|(p, &stack)| (-(1.0 - rng.gen::<f64>()).ln() / stack, p)).collect();
+        order.select_nth_unstable_by(paid.min(stacks.len() - 1), |a, b| a.0.total_cmp(&b.0));
+        order[..paid].sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (place, &(_, p)) in order[..paid].iter().enumerate() {
+            tally.totals[p] += prize(place);
+        }
+    }));
+    tally.totals.into_iter().map(|total| total / sim.trials as f64).collect()
+}
+
+/// Two players about to get all in, and the chips already in the middle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AllIn {
+    /// Index of the player making the decision
+    pub hero: usize,
+    /// Index of the player hero is all in against
+    pub villain: usize,
+    /// Chips in the middle apart from what hero and villain have put in on
+    /// this street, such as antes and the blinds of players who have folded
+    pub pot: f64,
+    /// Chips hero has already put in on this street
+    pub hero_invested: f64,
+    /// Chips villain has already put in on this street
+    pub villain_invested: f64,
+}
+
+/// Hero's $EV for folding and for getting all in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IcmDecision {
+    /// $EV if hero folds
+    pub fold: f64,
+    /// $EV if hero calls or shoves
+    pub all_in: f64,
+    /// Equity hero needs when all in for calling or shoving to be worth as
+    /// much as folding, ignoring split pots
+    pub required_equity: f64,
+}
+
+impl IcmDecision {
+    /// $EV gained by getting all in rather than folding
+    pub fn gain(&self) -> f64 {
+        self.all_in - self.fold
+    }
+}
+
+impl AllIn {
+    /// `hero` all in against `villain` with `pot` in the middle and nothing
+    /// yet put in on this street
+    pub fn new(hero: usize, villain: usize, pot: f64) -> AllIn {
+        AllIn{hero, villain, pot, hero_invested: 0.0, villain_invested: 0.0}
+    }
+
+    /// Record the chips each player has already put in on this street
+    pub fn invested(mut self, hero: f64, villain: f64) -> AllIn {
+        self.hero_invested = hero;
+        self.villain_invested = villain;
+        self
+    }
+
+    /// The all in as a heads up `Spot`, given every player's chips behind
+    fn spot(&self, stacks: &[f64]) -> Spot {
+        Spot::new(self.pot, stacks[self.hero], stacks[self.villain]).invested(self.hero_invested, self.villain_invested)
+    }
+
+    /// Every player's stack after hero folds, wins, ties and loses the all in
+    fn outcomes(&self, stacks: &[f64]) -> [Vec<f64>; 4] {
+        let spot = self.spot(stacks);
+        let committed = spot.effective_stack();
+        let final_pot = self.pot + 2.0 * committed;
+        let hero_left = stacks[self.hero] + self.hero_invested - committed;
+        let villain_left = stacks[self.villain] + self.villain_invested - committed;
+        let with = |hero: f64, villain: f64| {
+            let mut after = stacks.to_vec();
+            after[self.hero] = hero;
+            after[self.villain] = villain;
+            after
+        };
+        [
+            with(stacks[self.hero], stacks[self.villain] + self.villain_invested + self.hero_invested + self.pot),
+            with(hero_left + final_pot, villain_left),
+            with(hero_left + final_pot / 2.0, villain_left + final_pot / 2.0),
+            with(hero_left, villain_left + final_pot),
+        ]
+    }
+}
+
+/// Compare hero's $EV for folding and calling an all in, where `stacks` holds
+/// every player's chips behind and `equity` is hero's all in equity, which
+/// may come from any calculation in `equity`.
+///
+/// A call that wins chips on average can still lose money under ICM, since
+/// the chips hero stands to lose are worth more than the chips hero stands
+/// to win.
+///
+/// # Example
+/// ```
+/// use rusty_poker::equity::PlayerEquity;
+/// use rusty_poker::icm::*;
+///
+/// // Two big stacks flip for it on the bubble of a three handed payout
+/// let stacks = [1000.0, 1000.0, 1000.0, 200.0];
+/// let flip = PlayerEquity{pwin: 0.5, ptie: 0.0, equity: 0.5, std_error: 0.0};
+/// let call = icm_call(&stacks, &[50.0, 30.0, 20.0], &AllIn::new(0, 1, 0.0), &flip, IcmMode::Exact).unwrap();
+/// assert!(call.gain() < 0.0);
+/// assert!(call.required_equity > 0.5);
+/// ```
+pub fn icm_call(stacks: &[f64], payouts: &[f64], all_in: &AllIn, equity: &PlayerEquity, mode: IcmMode) -> Result<IcmDecision, IcmError> {
+    icm_shove(stacks, payouts, all_in, equity, 0.0, mode)
+}
+
+/// Compare hero's $EV for folding and shoving all in, where villain folds to
+/// the shove `fold_frequency` of the time and otherwise calls with hero
+/// holding `equity`. The required equity is what hero needs when called.
+pub fn icm_shove(stacks: &[f64], payouts: &[f64], all_in: &AllIn, equity: &PlayerEquity, fold_frequency: f64, mode: IcmMode) -> Result<IcmDecision, IcmError> {
+    if all_in.hero == all_in.villain || all_in.hero.max(all_in.villain) >= stacks.len() {
+        return Err(IcmError::InvalidPlayers(all_in.hero, all_in.villain));
+    }
+    let hero = all_in.hero;
+    let mut value = [0.0; 4];
+    for (value, after) in value.iter_mut().zip(all_in.outcomes(stacks)) {
+        *value = icm_equity(&after, payouts, mode)?[hero];
+    }
+    let [folded, won, tied, lost] = value;
+    // $EV when villain folds to hero's shove
+    let steal = icm_equity(&with_pot_won(stacks, all_in), payouts, mode)?[hero];
+    let ploss = 1.0 - equity.pwin - equity.ptie;
+    let called = equity.pwin * won + equity.ptie * tied + ploss * lost;
+    let required_equity = if fold_frequency < 1.0 {
+        let needed_when_called = (folded - fold_frequency * steal) / (1.0 - fold_frequency);
+        ((needed_when_called - lost) / (won - lost)).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    Ok(IcmDecision{fold: folded, all_in: fold_frequency * steal + (1.0 - fold_frequency) * called, required_equity})
+}
+
+/// Every player's stack after villain folds and hero takes the middle
+fn with_pot_won(stacks: &[f64], all_in: &AllIn) -> Vec<f64> {
+    let mut after = stacks.to_vec();
+    after[all_in.hero] += all_in.pot + all_in.hero_invested + all_in.villain_invested;
+    after
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn three_handed() {
+        let ev = icm_equity(&[50.0, 30.0, 20.0], &[0.5, 0.3, 0.2], IcmMode::Exact).unwrap();
+        // First 0.5, second 0.3 * 50/70 + 0.2 * 50/80, third what's left
+        let second = 0.3 * 50.0 / 70.0 + 0.2 * 50.0 / 80.0;
+        let expected = 0.5 * 0.5 + 0.3 * second + 0.2 * (1.0 - 0.5 - second);
+        assert!((ev[0] - expected).abs() < 1e-12);
+        assert!((ev.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+
+        // Equal stacks are worth the same, and winner take all is chip EV
+        let ev = icm_equity(&[10.0; 5], &[0.5, 0.3, 0.2], IcmMode::Exact).unwrap();
+        assert!(ev.iter().all(|value| (value - 0.2).abs() < 1e-12));
+        let ev = icm_equity(&[60.0, 30.0, 10.0], &[1.0], IcmMode::Exact).unwrap();
+        assert!((ev[1] - 0.3).abs() < 1e-12);
+    }
+
+    #[test]
+    fn busted_players_take_the_bottom_places() {
+        let ev = icm_equity(&[50.0, 0.0, 50.0, 0.0], &[0.5, 0.3, 0.15, 0.05], IcmMode::Exact).unwrap();
+        assert_eq!(ev[1], 0.1);
+        assert_eq!(ev[3], 0.1);
+        assert!((ev[0] - 0.4).abs() < 1e-12);
+
+        assert_eq!(icm_equity(&[], &[1.0], IcmMode::Exact), Err(IcmError::NoPlayers));
+        assert_eq!(icm_equity(&[0.0, 0.0], &[1.0], IcmMode::Exact), Err(IcmError::NoChips));
+        assert_eq!(icm_equity(&[10.0, -1.0], &[1.0], IcmMode::Exact), Err(IcmError::InvalidStack(-1.0)));
+        assert_eq!(icm_equity(&[1.0; 21], &[1.0], IcmMode::Exact), Err(IcmError::TooManyPlayers(21)));
+    }
+
+    #[test]
+    fn sampling_matches_the_recursion() {
+        let stacks = [40.0, 25.0, 15.0, 10.0, 6.0, 4.0];
+        let payouts = [0.4, 0.25, 0.15, 0.1];
+        let exact = icm_equity(&stacks, &payouts, IcmMode::Exact).unwrap();
+        let sampled = icm_equity(&stacks, &payouts, IcmMode::Sampled(Simulation::new(200_000).seed(3))).unwrap();
+        for (exact, sampled) in exact.iter().zip(&sampled) {
+            assert!((exact - sampled).abs() < 0.005);
+        }
+
+        // Large fields can only be sampled
+        let field: Vec<f64> = (1..=100).map(|stack| stack as f64).collect();
+        let sampled = icm_equity(&field, &[0.5, 0.3, 0.2], IcmMode::Sampled(Simulation::new(10_000).seed(1))).unwrap();
+        assert!((sampled.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(sampled[99] > sampled[0]);
+    }
+
+    #[test]
+    fn all_in_decisions() {
+        let stacks = [1000.0, 1000.0, 1000.0, 200.0];
+        let payouts = [50.0, 30.0, 20.0];
+        let flip = PlayerEquity{pwin: 0.5, ptie: 0.0, equity: 0.5, std_error: 0.0};
+        let call = icm_call(&stacks, &payouts, &AllIn::new(0, 1, 0.0), &flip, IcmMode::Exact).unwrap();
+        let before = icm_equity(&stacks, &payouts, IcmMode::Exact).unwrap();
+        assert!((call.fold - before[0]).abs() < 1e-12);
+        assert!(call.gain() < 0.0);
+
+        // At the required equity calling and folding are worth the same
+        let p = call.required_equity;
+        let even = PlayerEquity{pwin: p, ptie: 0.0, equity: p, std_error: 0.0};
+        let call = icm_call(&stacks, &payouts, &AllIn::new(0, 1, 0.0), &even, IcmMode::Exact).unwrap();
+        assert!(call.gain().abs() < 1e-9);
+
+        // A shove that always gets through steals the blinds
+        let blinds = AllIn::new(3, 0, 30.0).invested(0.0, 20.0);
+        let shove = icm_shove(&stacks, &payouts, &blinds, &flip, 1.0, IcmMode::Exact).unwrap();
+        let mut after = stacks;
+        after[3] += 50.0;
+        assert!((shove.all_in - icm_equity(&after, &payouts, IcmMode::Exact).unwrap()[3]).abs() < 1e-12);
+        assert!(shove.gain() > 0.0);
+
+        assert_eq!(icm_call(&stacks, &payouts, &AllIn::new(1, 1, 0.0), &flip, IcmMode::Exact), Err(IcmError::InvalidPlayers(1, 1)));
+    }
+}
@@ -0,0 +1,320 @@
+//! Reduce boards to their strategically distinct forms.
+//!
+//! Two boards are isomorphic if one can be turned into the other by relabeling
+//! suits. Isomorphic boards play identically, so aggregate reports only need
+//! to look at one representative of each class, weighted by the number of raw
+//! boards in the class.
+
+use crate::card::*;
+use crate::texture::BoardTexture;
+
+/// A representative board and the number of raw boards it stands for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeightedBoard {
+    pub cards: Vec<Card>,
+    pub weight: u32,
+}
+
+/// Return all 24 relabelings of the suits. Each permutation maps the suit at
+/// index `suit as usize` to the suit stored there.
+pub fn suit_permutations() -> Vec<[Suit; 4]> {
+    let mut perms = Vec::with_capacity(24);
+    for a in suits().iter() {
+        for b in suits().iter() {
+            for c in suits().iter() {
+                for d in suits().iter() {
+                    let perm = [*a, *b, *c, *d];
+                    let distinct = (0..4).all(|i| (i + 1..4).all(|j| perm[i] != perm[j]));
+                    if distinct {
+                        perms.push(perm);
+                    }
+                }
+            }
+        }
+    }
+    perms
+}
+
+/// Relabel the suit of `card` according to `perm`
+pub fn permute_card(card: &Card, perm: &[Suit; 4]) -> Card {
+    Card{rank: card.rank, suit: perm[card.suit as usize]}
+}
+
+/// Return the canonical representative of the board's isomorphism class.
+///
+/// Boards are treated as unordered sets, so two boards are isomorphic exactly
+/// when their canonical forms are equal.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::isomorphism::*;
+///
+/// let a = canonical_board(&[ACE_SPADES, KING_SPADES, TWO_HEARTS]);
+/// let b = canonical_board(&[KING_CLUBS, TWO_DIAMONDS, ACE_CLUBS]);
+/// assert_eq!(a, b);
+/// ```
+pub fn canonical_board(cards: &[Card]) -> Vec<Card> {
+    canonical_under(cards, &suit_permutations())
+}
+
+fn canonical_under(cards: &[Card], perms: &[[Suit; 4]]) -> Vec<Card> {
+    perms.iter()
+        .map(|perm| sorted_by_index(cards.iter().map(|card| permute_card(card, perm)).collect()))
+        .min_by_key(|board| board_key(board))
+        .unwrap()
+}
+
+/// Return the 1,755 strategically distinct flops along with how many of the
+/// 22,100 raw flops each one represents.
+pub fn canonical_flops() -> Vec<WeightedBoard> {
+    let cards = all_cards();
+    let perms = suit_permutations();
+    let mut weights: Vec<u32> = vec![0; 52 * 52 * 52];
+    let mut flops: Vec<Vec<Card>> = Vec::new();
+    for (i, c1) in cards.iter().enumerate() {
+        for (j, c2) in cards.iter().enumerate().skip(i + 1) {
+            for c3 in &cards[j + 1..] {
+                let flop = canonical_under(&[*c1, *c2, *c3], &perms);
+                let key = (flop[0].index() * 52 + flop[1].index()) * 52 + flop[2].index();
+                if weights[key] == 0 {
+                    flops.push(flop);
+                }
+                weights[key] += 1;
+            }
+        }
+    }
+    flops.into_iter()
+        .map(|flop| {
+            let key = (flop[0].index() * 52 + flop[1].index()) * 52 + flop[2].index();
+            WeightedBoard{cards: flop, weight: weights[key]}
+        })
+        .collect()
+}
+
+/// Return the strategically distinct turns for `flop`, each weighted by the
+/// number of the 49 raw turn cards it represents.
+///
+/// The flop is kept exactly as given, so only relabelings that leave the flop
+/// unchanged are used to group turn cards. The turn card is the last card of
+/// each returned board.
+pub fn canonical_turns(flop: &[Card]) -> Vec<WeightedBoard> {
+    let flop_key = board_key(&sorted_by_index(flop.to_vec()));
+    let stabilizer: Vec<[Suit; 4]> = suit_permutations().into_iter()
+        .filter(|perm| {
+            let permuted = sorted_by_index(flop.iter().map(|card| permute_card(card, perm)).collect());
+            board_key(&permuted) == flop_key
+        })
+        .collect();
+
+    let mut turns: Vec<WeightedBoard> = Vec::new();
+    for card in remaining_cards(flop) {
+        let rep = stabilizer.iter()
+            .map(|perm| permute_card(&card, perm))
+            .min_by_key(|card| card.index())
+            .unwrap();
+        match turns.iter_mut().find(|turn| turn.cards[flop.len()] == rep) {
+            Some(turn) => turn.weight += 1,
+            None => {
+                let mut cards = flop.to_vec();
+                cards.push(rep);
+                turns.push(WeightedBoard{cards, weight: 1});
+            }
+        }
+    }
+    turns
+}
+
+/// Return a subset of `n` flops that stands in for all 22,100 raw flops.
+///
+/// The canonical flops are stratified by texture: suit pattern, pairing, high
+/// card and connectedness, dropping the finer properties from the back until
+/// there are no more strata than `n`. The `n` picks are shared between the
+/// strata in proportion to the number of raw flops in each, so every stratum
+/// gets at least one. Within a stratum the flops are ordered by rank and split
+/// into consecutive groups of roughly equal weight, and the weighted median of
+/// each group represents it while carrying the group's total weight. The
+/// weights of the subset therefore still sum to 22,100. If `n` is at least
+/// 1,755 every canonical flop is returned.
+pub fn representative_flops(n: usize) -> Vec<WeightedBoard> {
+    let flops = canonical_flops();
+    if n >= flops.len() {
+        return flops;
+    }
+    assert!(n > 0, "Must ask for at least one flop");
+
+    let keys: Vec<Vec<usize>> = flops.iter()
+        .map(|flop| {
+            let texture = BoardTexture::new(&flop.cards);
+            vec![texture.max_suit_count, texture.pairing as usize, texture.high_card.rank as usize, texture.connectedness]
+        })
+        .collect();
+    let strata = (0..=STRATUM_PROPERTIES).rev()
+        .map(|properties| stratify(&flops, &keys, properties))
+        .find(|strata| strata.len() <= n)
+        .unwrap();
+
+    // Share out the picks by the Sainte-Laguë method, which keeps each
+    // stratum's share of the picks close to its share of the weight
+    let weights: Vec<u32> = strata.iter().map(|stratum| stratum.iter().map(|flop| flop.weight).sum()).collect();
+    let mut picks = vec![1; strata.len()];
+    for _ in strata.len()..n {
+        let next = (0..strata.len())
+            .filter(|&i| picks[i] < strata[i].len())
+            .max_by(|&i, &j| {
+                let priority = |k: usize| weights[k] as f64 / (2 * picks[k] + 1) as f64;
+                priority(i).partial_cmp(&priority(j)).unwrap()
+            })
+            .unwrap();
+        picks[next] += 1;
+    }
+
+    strata.into_iter()
+        .zip(picks)
+        .flat_map(|(stratum, picks)| split_stratum(stratum, picks))
+        .collect()
+}
+
+/// Number of texture properties used to stratify flops in
+/// `representative_flops`
+const STRATUM_PROPERTIES: usize = 4;
+
+/// Group `flops` by the first `properties` entries of their texture keys,
+/// ordering the flops of each stratum from the highest ranks down
+fn stratify(flops: &[WeightedBoard], keys: &[Vec<usize>], properties: usize) -> Vec<Vec<WeightedBoard>> {
+    let mut strata: Vec<(&[usize], Vec<WeightedBoard>)> = Vec::new();
+    for (flop, key) in flops.iter().zip(keys) {
+        let key = &key[..properties];
+        match strata.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, stratum)) => stratum.push(flop.clone()),
+            None => strata.push((key, vec![flop.clone()])),
+        }
+    }
+    strata.into_iter()
+        .map(|(_, mut stratum)| {
+            stratum.sort_by_key(|flop| {
+                let mut ranks: Vec<Rank> = flop.cards.iter().map(|card| card.rank).collect();
+                ranks.sort_unstable_by(|a, b| b.cmp(a));
+                (ranks, board_key(&flop.cards))
+            });
+            stratum
+        })
+        .collect()
+}
+
+/// Split a stratum into `n` consecutive groups of roughly equal weight, each
+/// represented by its weighted median flop
+fn split_stratum(stratum: Vec<WeightedBoard>, n: usize) -> Vec<WeightedBoard> {
+    let total: u32 = stratum.iter().map(|flop| flop.weight).sum();
+    let count = stratum.len();
+    let mut subset: Vec<WeightedBoard> = Vec::with_capacity(n);
+    let mut group: Vec<WeightedBoard> = Vec::new();
+    let mut seen = 0;
+    for (i, flop) in stratum.into_iter().enumerate() {
+        seen += flop.weight;
+        group.push(flop);
+        let boundary = (subset.len() as u64 + 1) * total as u64 / n as u64;
+        // A heavy flop can carry a group past more than one boundary, so once
+        // only enough flops are left for one per remaining group every flop
+        // closes a group of its own
+        let short = count - i <= n - subset.len();
+        if seen as u64 >= boundary || short {
+            let weight: u32 = group.iter().map(|flop| flop.weight).sum();
+            let mut below = 0;
+            let median = group.iter()
+                .find(|flop| {
+                    below += flop.weight;
+                    2 * below >= weight
+                })
+                .unwrap();
+            subset.push(WeightedBoard{cards: median.cards.clone(), weight});
+            group.clear();
+        }
+    }
+    subset
+}
+
+fn sorted_by_index(mut cards: Vec<Card>) -> Vec<Card> {
+    cards.sort_by_key(|card| card.index());
+    cards
+}
+
+fn board_key(cards: &[Card]) -> Vec<usize> {
+    cards.iter().map(|card| card.index()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    use crate::preflop::HandClass;
+    use crate::showdown::showdown;
+    use super::*;
+
+    #[test]
+    fn flop_weights() {
+        let flops = canonical_flops();
+        assert_eq!(flops.len(), 1755);
+        assert_eq!(flops.iter().map(|flop| flop.weight).sum::<u32>(), 22100);
+        let weight_of = |board: &[Card]| {
+            let canonical = canonical_board(board);
+            flops.iter().find(|flop| flop.cards == canonical).unwrap().weight
+        };
+        assert_eq!(weight_of(&[ACE_SPADES, KING_SPADES, QUEEN_SPADES]), 4);
+        assert_eq!(weight_of(&[ACE_SPADES, KING_CLUBS, QUEEN_HEARTS]), 24);
+        assert_eq!(weight_of(&[ACE_SPADES, KING_SPADES, QUEEN_HEARTS]), 12);
+        assert_eq!(weight_of(&[ACE_SPADES, ACE_CLUBS, KING_SPADES]), 12);
+        assert_eq!(weight_of(&[ACE_SPADES, ACE_CLUBS, ACE_HEARTS]), 4);
+    }
+
+    #[test]
+    fn turn_weights() {
+        // On a monotone flop the three other suits are interchangeable
+        let turns = canonical_turns(&[ACE_SPADES, KING_SPADES, QUEEN_SPADES]);
+        assert_eq!(turns.len(), 10 + 13);
+        assert_eq!(turns.iter().map(|turn| turn.weight).sum::<u32>(), 49);
+        // On a rainbow flop with distinct ranks nothing can be swapped
+        let turns = canonical_turns(&[ACE_SPADES, KING_CLUBS, QUEEN_HEARTS]);
+        assert_eq!(turns.len(), 49);
+        assert!(turns.iter().all(|turn| turn.cards[..3] == [ACE_SPADES, KING_CLUBS, QUEEN_HEARTS]));
+    }
+
+    #[test]
+    fn representative_subsets() {
+        for n in [1754, 1700, 1000, 184, 95, 1].iter() {
+            let subset = representative_flops(*n);
+            assert_eq!(subset.len(), *n);
+            assert_eq!(subset.iter().map(|flop| flop.weight).sum::<u32>(), 22100);
+        }
+        assert_eq!(representative_flops(2000).len(), 1755);
+    }
+
+    #[test]
+    fn representative_subsets_keep_equity() {
+        // AKs against QQ with the flop as the whole board. Averaging over
+        // every combo of both classes makes this the same for all flops of a
+        // class, and it moves with pairing, suits and high card.
+        let hero = HandClass::new(Rank::Ace, Rank::King, true).combos();
+        let villain = HandClass::new(Rank::Queen, Rank::Queen, false).combos();
+        let equity = |flops: &[WeightedBoard]| {
+            let (mut won, mut dealt) = (0.0, 0.0);
+            for flop in flops {
+                for h1 in &hero {
+                    for h2 in &villain {
+                        let cards = [h1[0], h1[1], h2[0], h2[1]];
+                        if cards.iter().any(|card| flop.cards.contains(card)) || h1.iter().any(|card| h2.contains(card)) {
+                            continue;
+                        }
+                        won += flop.weight as f64 * showdown(&[*h1, *h2], &flop.cards).shares()[0];
+                        dealt += flop.weight as f64;
+                    }
+                }
+            }
+            won / dealt
+        };
+        let full = equity(&canonical_flops());
+        for (n, tolerance) in [(1000, 0.005), (184, 0.01), (95, 0.03)].iter() {
+            let subset = equity(&representative_flops(*n));
+            assert!((subset - full).abs() < *tolerance, "{} flops: {} against {}", n, subset, full);
+        }
+    }
+}
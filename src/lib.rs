@@ -1,4 +1,20 @@
+pub mod cache;
 pub mod card;
+pub mod decision;
 pub mod equity;
+pub mod frequency;
 pub mod hand_type;
+pub mod icm;
+pub mod isomorphism;
+pub mod multi_board;
+pub mod nuts;
+pub mod preflop;
+pub mod range;
+pub mod run_it;
+pub mod showdown;
+pub mod simulation;
+pub mod street;
+pub mod strength;
+pub mod texture;
 pub mod util;
+pub mod variant;
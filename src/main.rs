@@ -1,20 +1,34 @@
 use std::env;
-use rusty_poker::card::HoleCards;
-use rusty_poker::equity::hand_vs_hand;
+use rusty_poker::card::{parse_cards, HoleCards};
+use rusty_poker::equity::hand_vs_hand_report;
+use rusty_poker::simulation::Simulation;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let args = &args[1..];
-    if args.len() != 2 {
-        panic!("Usage: hand-v-hand h1 h2")
+    if args.len() < 2 || args.len() > 4 {
+        panic!("Usage: hand-v-hand h1 h2 [board [dead]]")
     }
 
-    let h1 = args.get(0).unwrap();
-    let h2 = args.get(1).unwrap();
+    let h1 = &args[0];
+    let h2 = &args[1];
 
     let h1_cards: HoleCards = [h1[0..2].parse().unwrap(), h1[2..].parse().unwrap()];
     let h2_cards: HoleCards = [h2[0..2].parse().unwrap(), h2[2..].parse().unwrap()];
+    let board = match args.get(2) {
+        Some(board) => parse_cards(board).unwrap(),
+        None => vec![],
+    };
+    let dead = match args.get(3) {
+        Some(dead) => parse_cards(dead).unwrap(),
+        None => vec![],
+    };
 
-    let result = hand_vs_hand(&h1_cards, &h2_cards, &vec![], 10000);
-    println!("{}: {}\n{}: {}\ntie:  {}", h1, result[0].pwin, h2, result[1].pwin, result[0].pdraw);
+    let report = hand_vs_hand_report(&h1_cards, &h2_cards, &board, &dead, &Simulation::new(10000).all_cores()).unwrap();
+    let players = &report.players;
+    println!("{}: {}\n{}: {}\ntie:  {}", h1, players[0].pwin, h2, players[1].pwin, players[0].ptie);
+    if players[0].std_error > 0.0 {
+        let (low, high) = players[0].confidence_interval();
+        println!("{} equity 95% CI: {} to {}", h1, low, high);
+    }
 }
@@ -0,0 +1,207 @@
+//! Equity when the pot is split between two or more boards, as in double board
+//! bomb pots
+
+use crate::card::*;
+use crate::equity::*;
+use crate::showdown::*;
+use crate::util::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::time::{Duration, Instant};
+
+/// One player's results across every board
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiBoardPlayer {
+    /// Equity on each board alone, in the order the boards were given
+    pub boards: Vec<PlayerEquity>,
+    /// Probability of winning every board outright and taking the whole pot
+    pub pscoop: f64,
+    /// Share of the whole pot, with each board worth an equal part of it.
+    /// `pwin` is the scoop probability and `ptie` the probability of taking
+    /// some but not all of the pot.
+    pub equity: PlayerEquity,
+    /// Counts behind `equity`: a win is a scoop and a tie any partial share
+    pub counts: EquityCounts,
+}
+
+/// The result of a multi board equity calculation
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiBoardEquity {
+    /// Each player's results, in the order the hands were given
+    pub players: Vec<MultiBoardPlayer>,
+    /// Number of deals shown down
+    pub trials: u64,
+    /// Wall clock time spent on the calculation
+    pub elapsed: Duration,
+}
+
+/// Compute every player's equity when each of `boards` is completed from the
+/// same deck and the pot is split equally between the boards. Boards may be
+/// empty, as in a bomb pot dealt before the flop, or partly dealt. Cards in
+/// `dead` are never dealt.
+///
+/// In `EquityMode::Exact` every way of completing the boards is shown down,
+/// which is feasible from the turn, or from the flop heads up.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::EquityMode;
+/// use rusty_poker::multi_board::*;
+///
+/// let hands = [[ACE_SPADES, ACE_CLUBS], [SEVEN_HEARTS, EIGHT_HEARTS]];
+/// let boards = [
+///     vec![ACE_HEARTS, KING_DIAMONDS, TWO_CLUBS, THREE_SPADES, NINE_CLUBS],
+///     vec![FOUR_HEARTS, FIVE_HEARTS, SIX_CLUBS, KING_CLUBS, QUEEN_DIAMONDS],
+/// ];
+/// let result = multi_board_equity(&hands, &boards, &[], EquityMode::Exact).unwrap();
+/// // Trip aces take the first board and the straight the second
+/// assert_eq!(result.players[0].boards[0].pwin, 1.0);
+/// assert_eq!(result.players[1].boards[1].pwin, 1.0);
+/// assert_eq!(result.players[0].equity.equity, 0.5);
+/// assert_eq!(result.players[0].pscoop, 0.0);
+/// ```
+pub fn multi_board_equity(hands: &[HoleCards], boards: &[Vec<Card>], dead: &[Card], mode: EquityMode) -> Result<MultiBoardEquity, EquityError> {
+    if hands.is_empty() {
+        return Err(EquityError::NoPlayers);
+    }
+    if boards.is_empty() {
+        return Err(EquityError::NoBoards);
+    }
+    if let Some(board) = boards.iter().find(|board| board.len() > 5) {
+        return Err(EquityError::InvalidBoard(board.len()));
+    }
+    let start = Instant::now();
+    let known: Vec<Card> = boards.iter().flatten().chain(dead).copied().collect();
+    let deck = live_deck(hands, &[], &known)?;
+    check_trials(mode)?;
+    let needed: Vec<usize> = boards.iter().map(|board| 5 - board.len()).collect();
+    if needed.iter().sum::<usize>() > deck.len() {
+        return Err(EquityError::TooManyBoards(boards.len()));
+    }
+
+    // Each player's counts on every board, followed by their counts for the
+    // whole pot
+    let init = || vec![vec![EquityCounts::default(); boards.len() + 1]; hands.len()];
+    let record = |tally: &mut Vec<Vec<EquityCounts>>, full_boards: &[Vec<Card>]| {
+        let mut pot = vec![0.0; hands.len()];
+        for (b, full_board) in full_boards.iter().enumerate() {
+            let result = showdown(hands, full_board);
+            for (p, share) in result.shares().into_iter().enumerate() {
+                tally[p][b].record(share, result.is_split());
+                pot[p] += share / full_boards.len() as f64;
+            }
+        }
+        for (p, share) in pot.into_iter().enumerate() {
+            let scoop = (share - 1.0).abs() < 1e-9;
+            tally[p][full_boards.len()].record(if scoop { 1.0 } else { share }, !scoop);
+        }
+    };
+
+    let tally = match mode {
+        EquityMode::Exact => {
+            let mut tally = init();
+            for_each_board_deal(&deck, boards, |full_boards| record(&mut tally, full_boards));
+            tally
+        }
+        _ => sample(mode, init, |rng: &mut StdRng, tally: &mut Vec<Vec<EquityCounts>>| {
+            let mut dealt = deck.choose_multiple(rng, needed.iter().sum()).copied();
+            let full_boards: Vec<Vec<Card>> = boards.iter().zip(&needed).map(|(board, &n)| board.iter().copied().chain(dealt.by_ref().take(n)).collect()).collect();
+            record(tally, &full_boards);
+        }, |_| true),
+    };
+
+    let trials = tally[0][0].trials;
+    let players = tally.into_iter().map(|mut counts| {
+        let pot = counts.pop().unwrap();
+        let mut player = MultiBoardPlayer {
+            boards: counts.iter().map(|counts| counts.player_equity()).collect(),
+            pscoop: pot.pwin(),
+            equity: pot.player_equity(),
+            counts: pot,
+        };
+        if mode == EquityMode::Exact {
+            for equity in player.boards.iter_mut().chain(std::iter::once(&mut player.equity)) {
+                equity.std_error = 0.0;
+            }
+        }
+        player
+    }).collect();
+    Ok(MultiBoardEquity{players, trials, elapsed: start.elapsed()})
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    use crate::simulation::Simulation;
+    use super::*;
+
+    #[test]
+    fn double_board_on_the_turn() {
+        // Set of aces against a made straight, with each hand owning one
+        // board unless the second board pairs and fills up the aces
+        let hands = [[ACE_CLUBS, ACE_SPADES], [NINE_HEARTS, EIGHT_HEARTS]];
+        let boards = [
+            vec![ACE_HEARTS, KING_DIAMONDS, TWO_CLUBS, THREE_SPADES],
+            vec![SIX_HEARTS, SEVEN_CLUBS, TEN_DIAMONDS, ACE_DIAMONDS],
+        ];
+        let result = multi_board_equity(&hands, &boards, &[], EquityMode::Exact).unwrap();
+        assert_eq!(result.trials, 40 * 39);
+
+        // Each board alone plays as if the other board's cards were dead
+        for (b, board) in boards.iter().enumerate() {
+            let other = &boards[1 - b];
+            let alone = multiway_equity(&hands, board, other, EquityMode::Exact).unwrap();
+            for p in 0..2 {
+                assert!((result.players[p].boards[b].equity - alone.players[p].equity).abs() < 1e-12);
+            }
+        }
+
+        // Nine rivers pair the second board, and otherwise the pot is split
+        let (aces, straight) = (&result.players[0], &result.players[1]);
+        assert_eq!(aces.boards[0].pwin, 1.0);
+        assert!((aces.boards[1].pwin - 9.0 / 40.0).abs() < 1e-12);
+        assert!((aces.pscoop - 9.0 / 40.0).abs() < 1e-12);
+        assert_eq!(straight.pscoop, 0.0);
+        assert!((aces.equity.equity - (1.0 + 9.0 / 40.0) / 2.0).abs() < 1e-12);
+        assert!((aces.equity.equity + straight.equity.equity - 1.0).abs() < 1e-12);
+        assert_eq!(aces.counts.ties, 31 * 39);
+        assert_eq!(straight.counts.ties, 31 * 39);
+        assert_eq!(straight.counts.losses, 9 * 39);
+        assert_eq!(aces.counts.wins + aces.counts.ties + aces.counts.losses, result.trials);
+    }
+
+    #[test]
+    fn one_board_matches_multiway_equity() {
+        let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS], [SEVEN_HEARTS, EIGHT_HEARTS]];
+        let board = vec![KING_HEARTS, SEVEN_DIAMONDS, TWO_HEARTS, FOUR_CLUBS];
+        let result = multi_board_equity(&hands, std::slice::from_ref(&board), &[], EquityMode::Exact).unwrap();
+        let single = multiway_equity(&hands, &board, &[], EquityMode::Exact).unwrap();
+        for (player, equity) in result.players.iter().zip(&single.players) {
+            assert_eq!(&player.boards[0], equity);
+            assert_eq!(player.pscoop, equity.pwin);
+        }
+    }
+
+    #[test]
+    fn sampled_bomb_pot() {
+        let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS], [SEVEN_HEARTS, EIGHT_HEARTS]];
+        let sim = Simulation::new(5000).seed(9);
+        let result = multi_board_equity(&hands, &[vec![], vec![]], &[], EquityMode::MonteCarlo(sim)).unwrap();
+        assert_eq!(result.trials, 5000);
+        let total: f64 = result.players.iter().map(|player| player.equity.equity).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!(result.players.iter().all(|player| player.pscoop <= player.boards[0].pwin + 1e-12));
+        assert!(result.players[0].equity.std_error > 0.0);
+    }
+
+    #[test]
+    fn rejects_bad_boards() {
+        let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS]];
+        assert_eq!(multi_board_equity(&hands, &[], &[], EquityMode::Exact).unwrap_err(), EquityError::NoBoards);
+        let boards = [vec![KING_HEARTS, SEVEN_DIAMONDS, TWO_HEARTS], vec![KING_HEARTS, NINE_CLUBS, TEN_CLUBS]];
+        assert_eq!(multi_board_equity(&hands, &boards, &[], EquityMode::Exact).unwrap_err(), EquityError::DuplicateCard(KING_HEARTS));
+        // Ten empty boards need more than the 48 cards left
+        assert_eq!(multi_board_equity(&hands, &vec![vec![]; 10], &[], EquityMode::Exact).unwrap_err(), EquityError::TooManyBoards(10));
+    }
+}
@@ -0,0 +1,178 @@
+//! Rank every holding on a board from the nuts down to the worst hand
+
+use crate::card::*;
+use crate::hand_type::*;
+use crate::util::*;
+use std::cmp::{Ordering, Reverse};
+
+/// A group of holdings that all make a hand of exactly the same strength
+#[derive(Debug, Clone)]
+pub struct HandTier {
+    /// The hand made by the first holding in the tier. Every other holding in
+    /// the tier makes a hand that compares equal to it.
+    pub hand_type: HandType,
+    /// Every holding that makes a hand of this strength
+    pub combos: Vec<HoleCards>,
+}
+
+/// Where a single holding sits among all other holdings on a board.
+///
+/// Counts only include combos that do not share a card with the holding, since
+/// those are the only combos an opponent can actually hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HoldingRank {
+    /// Index of the holding's tier, where tier 0 is the nuts
+    pub tier: usize,
+    /// Number of combos that beat the holding
+    pub ahead: usize,
+    /// Number of other combos that tie the holding
+    pub tied: usize,
+    /// Number of combos that the holding beats
+    pub behind: usize,
+}
+
+/// Every holding not blocked by a board, grouped into tiers of equal strength
+/// and sorted from strongest to weakest.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::nuts::*;
+///
+/// let board = vec![ACE_SPADES, KING_SPADES, QUEEN_SPADES, TWO_DIAMONDS, THREE_CLUBS];
+/// let ranking = NutRanking::new(&board);
+/// assert_eq!(ranking.nuts().combos, vec![[TEN_SPADES, JACK_SPADES]]);
+///
+/// let rank = ranking.rank_of(&[ACE_HEARTS, ACE_DIAMONDS]).unwrap();
+/// assert!(rank.ahead > 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct NutRanking {
+    board: Vec<Card>,
+    tiers: Vec<HandTier>,
+}
+
+impl NutRanking {
+    /// Rank every holding on `board`, which must contain 3, 4 or 5 cards.
+    pub fn new(board: &[Card]) -> NutRanking {
+        assert!(board.len() >= 3 && board.len() <= 5, "Board must have 3 to 5 cards, found {}", board.len());
+        let mut ranked: Vec<(HandType, HoleCards)> = hole_card_combos(&remaining_cards(board))
+            .into_iter()
+            .map(|combo| (hand_type(&combo, board), combo))
+            .collect();
+        ranked.sort_by_key(|&(ht, _)| Reverse(ht));
+
+        let mut tiers: Vec<HandTier> = Vec::new();
+        for (ht, combo) in ranked {
+            match tiers.last_mut() {
+                Some(tier) if tier.hand_type.cmp(&ht) == Ordering::Equal => tier.combos.push(combo),
+                _ => tiers.push(HandTier{hand_type: ht, combos: vec![combo]}),
+            }
+        }
+        NutRanking{board: board.to_vec(), tiers}
+    }
+
+    /// The board these holdings were ranked on
+    pub fn board(&self) -> &[Card] {
+        &self.board
+    }
+
+    /// All tiers, from the nuts down to the weakest holdings
+    pub fn tiers(&self) -> &[HandTier] {
+        &self.tiers
+    }
+
+    /// The strongest possible holdings on this board
+    pub fn nuts(&self) -> &HandTier {
+        &self.tiers[0]
+    }
+
+    /// Total number of combos across all tiers
+    pub fn num_combos(&self) -> usize {
+        self.tiers.iter().map(|tier| tier.combos.len()).sum()
+    }
+
+    /// Index of the tier containing `hand`, or `None` if the hand is blocked
+    /// by the board.
+    pub fn tier_of(&self, hand: &HoleCards) -> Option<usize> {
+        self.tiers.iter().position(|tier| tier.combos.iter().any(|combo| same_holding(combo, hand)))
+    }
+
+    /// Rank `hand` against every combo it does not block, or return `None`
+    /// if the hand is blocked by the board.
+    pub fn rank_of(&self, hand: &HoleCards) -> Option<HoldingRank> {
+        let tier = self.tier_of(hand)?;
+        let count = |tiers: &[HandTier]| -> usize {
+            tiers.iter()
+                .flat_map(|t| t.combos.iter())
+                .filter(|combo| !cards_overlap(*combo, hand))
+                .count()
+        };
+        Some(HoldingRank{
+            tier,
+            ahead: count(&self.tiers[..tier]),
+            tied: count(&self.tiers[tier..=tier]),
+            behind: count(&self.tiers[tier + 1..]),
+        })
+    }
+
+    /// Every combo that beats `hand` and does not share a card with it, from
+    /// strongest to weakest. Empty if the hand is blocked by the board.
+    pub fn combos_beating(&self, hand: &HoleCards) -> Vec<HoleCards> {
+        let tier = match self.tier_of(hand) {
+            Some(tier) => tier,
+            None => return Vec::new(),
+        };
+        self.tiers[..tier].iter()
+            .flat_map(|t| t.combos.iter())
+            .filter(|combo| !cards_overlap(*combo, hand))
+            .cloned()
+            .collect()
+    }
+}
+
+fn same_holding(a: &HoleCards, b: &HoleCards) -> bool {
+    (a[0] == b[0] && a[1] == b[1]) || (a[0] == b[1] && a[1] == b[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    use super::*;
+
+    #[test]
+    fn every_combo_is_ranked() {
+        let ranking = NutRanking::new(&[ACE_SPADES, KING_HEARTS, SEVEN_DIAMONDS, SEVEN_CLUBS, TWO_HEARTS]);
+        assert_eq!(ranking.num_combos(), 1081);
+        for pair in ranking.tiers().windows(2) {
+            assert_eq!(pair[0].hand_type.cmp(&pair[1].hand_type), Ordering::Greater);
+        }
+    }
+
+    #[test]
+    fn nuts_on_paired_flop() {
+        let ranking = NutRanking::new(&[SEVEN_CLUBS, SEVEN_DIAMONDS, TWO_HEARTS]);
+        let nuts = ranking.nuts();
+        assert_eq!(nuts.combos.len(), 1);
+        assert!(matches!(nuts.hand_type, HandType::Quads(..)));
+        assert_eq!(ranking.rank_of(&[SEVEN_HEARTS, SEVEN_SPADES]).unwrap().ahead, 0);
+    }
+
+    #[test]
+    fn rank_excludes_blocked_combos() {
+        let board = [ACE_SPADES, KING_SPADES, QUEEN_SPADES, TWO_DIAMONDS, THREE_CLUBS];
+        let ranking = NutRanking::new(&board);
+        let hand = [JACK_HEARTS, TEN_HEARTS];
+        let rank = ranking.rank_of(&hand).unwrap();
+        // Only the royal and the other spade flushes beat a broadway straight
+        let beating = ranking.combos_beating(&hand);
+        assert_eq!(rank.ahead, beating.len());
+        assert!(beating.iter().all(|combo| combo.iter().all(|card| card.suit == Suit::Spades)));
+        assert_eq!(rank.ahead, 45);
+        // Every other unblocked JT that isn't JsTs ties
+        assert_eq!(rank.tied, 8);
+        // 91 of the 1081 combos contain the Jh or the Th
+        assert_eq!(rank.ahead + rank.tied + rank.behind, 990);
+        assert_eq!(ranking.rank_of(&[ACE_SPADES, TWO_CLUBS]), None);
+    }
+}
@@ -0,0 +1,441 @@
+//! Preflop starting hand classes and a precomputed table of their all-in
+//! equities against each other
+
+use crate::card::*;
+use crate::equity::*;
+use crate::isomorphism::*;
+use crate::range::*;
+use crate::util::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Number of distinct starting hand classes
+pub const NUM_CLASSES: usize = 169;
+
+const RANK_CHARS: &str = "23456789TJQKA";
+
+/// A starting hand with suits forgotten, such as `AKs`, `AKo` or `QQ`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandClass {
+    pub high: Rank,
+    pub low: Rank,
+    /// True for suited hands. Pairs are never suited.
+    pub suited: bool,
+}
+
+impl HandClass {
+    /// Return the class of two ranks in either order
+    pub fn new(a: Rank, b: Rank, suited: bool) -> HandClass {
+        let (high, low) = if (a as u32) >= (b as u32) { (a, b) } else { (b, a) };
+        HandClass{high, low, suited: suited && high != low}
+    }
+
+    /// Return the class of a particular pair of hole cards
+    pub fn of(cards: &HoleCards) -> HandClass {
+        HandClass::new(cards[0].rank, cards[1].rank, cards[0].suit == cards[1].suit)
+    }
+
+    /// Every class, in index order
+    pub fn all() -> Vec<HandClass> {
+        (0..NUM_CLASSES).map(HandClass::from_index).collect()
+    }
+
+    /// Position of the class in the usual 13x13 grid, read row by row: aces
+    /// first, pairs on the diagonal, suited hands above it and offsuit hands
+    /// below it
+    pub fn index(&self) -> usize {
+        let (high, low) = (14 - self.high as usize, 14 - self.low as usize);
+        if self.suited { high * 13 + low } else { low * 13 + high }
+    }
+
+    /// Return the class at `index` in the 13x13 grid
+    pub fn from_index(index: usize) -> HandClass {
+        let (row, col) = (index / 13, index % 13);
+        let rank = |i: usize| Rank::from_u32(14 - i as u32);
+        HandClass::new(rank(row), rank(col), row < col)
+    }
+
+    pub fn is_pair(&self) -> bool {
+        self.high == self.low
+    }
+
+    /// Every combo of hole cards in the class: 6 for a pair, 4 for a suited
+    /// hand and 12 for an offsuit hand
+    pub fn combos(&self) -> Vec<HoleCards> {
+        let mut combos = Vec::with_capacity(12);
+        for (i, &s1) in suits().iter().enumerate() {
+            for (j, &s2) in suits().iter().enumerate() {
+                let keep = if self.is_pair() { i < j } else { (i == j) == self.suited };
+                if keep {
+                    combos.push([Card{rank: self.high, suit: s1}, Card{rank: self.low, suit: s2}]);
+                }
+            }
+        }
+        combos
+    }
+}
+
+impl fmt::Display for HandClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rank_char = |rank: Rank| RANK_CHARS.as_bytes()[rank as usize - 2] as char;
+        write!(f, "{}{}", rank_char(self.high), rank_char(self.low))?;
+        if !self.is_pair() {
+            write!(f, "{}", if self.suited { 's' } else { 'o' })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseHandClassError(pub String);
+
+impl FromStr for HandClass {
+    type Err = ParseHandClassError;
+
+    /// Parse a class such as `"AKs"`, `"T9o"` or `"QQ"`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_uppercase();
+        let chars: Vec<char> = upper.chars().collect();
+        let rank = |c: char| RANK_CHARS.find(c).map(|n| Rank::from_u32(n as u32 + 2));
+        let (high, low) = match (chars.first().and_then(|c| rank(*c)), chars.get(1).and_then(|c| rank(*c))) {
+            (Some(high), Some(low)) => (high, low),
+            _ => return Err(ParseHandClassError(format!("Unrecognized hand class: \"{}\"", s))),
+        };
+        let suited = match (chars.get(2), high == low) {
+            (None, true) => false,
+            (Some('S'), false) => true,
+            (Some('O'), false) => false,
+            _ => return Err(ParseHandClassError(format!("Unrecognized hand class: \"{}\"", s))),
+        };
+        if chars.len() > 3 {
+            return Err(ParseHandClassError(format!("Unrecognized hand class: \"{}\"", s)));
+        }
+        Ok(HandClass::new(high, low, suited))
+    }
+}
+
+/// Compute the preflop all-in equity of one class against another.
+///
+/// Every pair of combos that can be dealt together counts equally, so an
+/// offsuit hand's twelve combos outweigh a suited hand's four. Combo pairs
+/// that are the same up to relabeling suits are only calculated once.
+pub fn class_vs_class(a: HandClass, b: HandClass, mode: EquityMode) -> Result<PlayerEquity, EquityError> {
+    let mut equities = Vec::new();
+    for (h1, h2, weight) in canonical_matchups(a, b) {
+        let report = multiway_equity(&[h1, h2], &[], &[], mode)?;
+        equities.push((weight as f64, report.players[0]));
+    }
+    Ok(weighted_equity(equities.into_iter()))
+}
+
+/// One representative of every distinct way the two classes' combos can be
+/// dealt against each other, with the number of combo pairs it stands for
+fn canonical_matchups(a: HandClass, b: HandClass) -> Vec<(HoleCards, HoleCards, u32)> {
+    let perms = suit_permutations();
+    let mut matchups: HashMap<[usize; 4], (HoleCards, HoleCards, u32)> = HashMap::new();
+    for h1 in a.combos() {
+        for h2 in b.combos() {
+            if cards_overlap(&h1, &h2) {
+                continue;
+            }
+            let key = perms.iter()
+                .map(|perm| {
+                    let mut p1 = [permute_card(&h1[0], perm).index(), permute_card(&h1[1], perm).index()];
+                    let mut p2 = [permute_card(&h2[0], perm).index(), permute_card(&h2[1], perm).index()];
+                    p1.sort_unstable();
+                    p2.sort_unstable();
+                    [p1[0], p1[1], p2[0], p2[1]]
+                })
+                .min()
+                .unwrap();
+            matchups.entry(key).or_insert((h1, h2, 0)).2 += 1;
+        }
+    }
+    let mut matchups: Vec<(HoleCards, HoleCards, u32)> = matchups.into_values().collect();
+    matchups.sort_by_key(|(h1, h2, _)| [h1[0].index(), h1[1].index(), h2[0].index(), h2[1].index()]);
+    matchups
+}
+
+/// Magic bytes at the start of a preflop table file
+const TABLE_MAGIC: &[u8; 4] = b"RPPT";
+const TABLE_VERSION: u8 = 1;
+
+/// The all-in equity of every hand class against every other.
+///
+/// Lookups ignore the suits of the hands involved and return the average over
+/// every combo pair of the two classes, so they don't account for card
+/// removal beyond the classes themselves. Tables built by sampling don't
+/// record their sampling error, and their lookups report a standard error of
+/// zero.
+///
+/// # Example
+/// ```no_run
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::EquityMode;
+/// use rusty_poker::preflop::*;
+/// use rusty_poker::simulation::Simulation;
+///
+/// // Takes a while even sampled, so build the table once and save it
+/// let mode = EquityMode::MonteCarlo(Simulation::new(10_000));
+/// let table = PreflopTable::generate_with_threads(mode, 8);
+/// table.save("preflop.bin").unwrap();
+///
+/// let table = PreflopTable::load("preflop.bin").unwrap();
+/// let aces: HandClass = "AA".parse().unwrap();
+/// let kings: HandClass = "KK".parse().unwrap();
+/// assert!(table.class_equity(aces, kings).equity > 0.8);
+/// let equity = table.hand_vs_hand(&[ACE_SPADES, KING_SPADES], &[QUEEN_HEARTS, QUEEN_DIAMONDS]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreflopTable {
+    /// Probability of winning and of tying for the row class against the
+    /// column class, indexed by `row * NUM_CLASSES + column`
+    entries: Vec<[f32; 2]>,
+}
+
+impl PreflopTable {
+    /// Compute every entry of the table with `class_vs_class` on a single
+    /// thread. See `generate_with_threads`.
+    pub fn generate(mode: EquityMode) -> PreflopTable {
+        PreflopTable::generate_with_threads(mode, 1)
+    }
+
+    /// Compute every entry of the table with `class_vs_class`, sharing the
+    /// matchups between `threads` threads.
+    ///
+    /// Each unordered pair of classes is computed once. With
+    /// `EquityMode::Exact` this enumerates every preflop runout for tens of
+    /// thousands of distinct matchups, which takes days on a single core.
+    /// A sampling mode's own threads run within each of these threads.
+    ///
+    /// # Panics
+    /// If `threads` is zero
+    pub fn generate_with_threads(mode: EquityMode, threads: usize) -> PreflopTable {
+        assert!(threads > 0, "Need at least one thread");
+        let classes = HandClass::all();
+        let pairs: Vec<(HandClass, HandClass)> = classes.iter().enumerate()
+            .flat_map(|(i, &a)| classes[i..].iter().map(move |&b| (a, b)))
+            .collect();
+        // Matchups differ a lot in how many combos they hold, so threads take
+        // the next pair as they finish rather than a fixed share
+        let next = AtomicUsize::new(0);
+        let (pairs, next) = (&pairs, &next);
+        let mut table = PreflopTable{entries: vec![[0.0; 2]; NUM_CLASSES * NUM_CLASSES]};
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| scope.spawn(move || {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(&(a, b)) = pairs.get(i) else { break };
+                        let equity = class_vs_class(a, b, mode).expect("Every pair of classes can be dealt");
+                        done.push((a, b, equity));
+                    }
+                    done
+                }))
+                .collect();
+            for worker in workers {
+                for (a, b, equity) in worker.join().unwrap() {
+                    table.set(a, b, &equity);
+                }
+            }
+        });
+        table
+    }
+
+    /// Record `a`'s equity against `b`, and the matching entry for `b`.
+    ///
+    /// A class against itself wins exactly as often as it loses, so its
+    /// single entry records the average of the two, which evens out any
+    /// sampling error.
+    fn set(&mut self, a: HandClass, b: HandClass, equity: &PlayerEquity) {
+        let (pwin, ptie) = (equity.pwin as f32, equity.ptie as f32);
+        let ploss = (1.0 - pwin - ptie).max(0.0);
+        if a == b {
+            self.entries[a.index() * NUM_CLASSES + a.index()] = [(pwin + ploss) / 2.0, ptie];
+            return;
+        }
+        self.entries[a.index() * NUM_CLASSES + b.index()] = [pwin, ptie];
+        self.entries[b.index() * NUM_CLASSES + a.index()] = [ploss, ptie];
+    }
+
+    /// Equity of class `a` against class `b`, averaged over every pair of
+    /// their combos that can be dealt together
+    pub fn class_equity(&self, a: HandClass, b: HandClass) -> PlayerEquity {
+        let [pwin, ptie] = self.entries[a.index() * NUM_CLASSES + b.index()];
+        let (pwin, ptie) = (pwin as f64, ptie as f64);
+        PlayerEquity{pwin, ptie, equity: pwin + ptie / 2.0, std_error: 0.0}
+    }
+
+    /// Equity of `h1` against `h2`, looked up by their classes.
+    ///
+    /// This is the class average, not the equity of these exact cards:
+    /// `AsKs` gets the same equity against `QhQd` as against `QsQd`, though the
+    /// queen of spades takes away some of its flushes.
+    pub fn hand_vs_hand(&self, h1: &HoleCards, h2: &HoleCards) -> PlayerEquity {
+        self.class_equity(HandClass::of(h1), HandClass::of(h2))
+    }
+
+    /// Equity of `hero` against a weighted range. Combos that share a card
+    /// with hero are removed, and the rest are looked up by class, so each
+    /// contributes its class average as in `hand_vs_hand`.
+    pub fn hand_vs_range(&self, hero: &HoleCards, villain: &Range) -> Result<PlayerEquity, EquityError> {
+        let villain = villain.without_blocked(hero);
+        if villain.combos().is_empty() {
+            return Err(EquityError::EmptyRange);
+        }
+        let hero = HandClass::of(hero);
+        let equities = villain.combos().iter().map(|combo| (combo.weight, self.class_equity(hero, HandClass::of(&combo.cards))));
+        Ok(weighted_equity(equities))
+    }
+
+    /// Write the table as a four byte magic number, a version byte and then
+    /// every entry's win and tie probabilities as little endian `f32`s
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(TABLE_MAGIC)?;
+        writer.write_all(&[TABLE_VERSION])?;
+        for entry in &self.entries {
+            writer.write_all(&entry[0].to_le_bytes())?;
+            writer.write_all(&entry[1].to_le_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Read a table written by `write_to`
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<PreflopTable> {
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != TABLE_MAGIC || header[4] != TABLE_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a preflop table file"));
+        }
+        let mut entries = Vec::with_capacity(NUM_CLASSES * NUM_CLASSES);
+        let mut bytes = [0u8; 8];
+        for _ in 0..NUM_CLASSES * NUM_CLASSES {
+            reader.read_exact(&mut bytes)?;
+            let pwin = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            let ptie = f32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+            entries.push([pwin, ptie]);
+        }
+        Ok(PreflopTable{entries})
+    }
+
+    /// Write the table to the file at `path`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Read a table from the file at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<PreflopTable> {
+        PreflopTable::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    use crate::simulation::Simulation;
+    use super::*;
+
+    fn class(s: &str) -> HandClass {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn hand_classes() {
+        let classes = HandClass::all();
+        assert_eq!(classes.len(), NUM_CLASSES);
+        assert!(classes.iter().enumerate().all(|(i, class)| class.index() == i));
+        assert_eq!(classes.iter().map(|class| class.combos().len()).sum::<usize>(), 1326);
+        assert_eq!(classes[0].to_string(), "AA");
+        assert_eq!(classes[1].to_string(), "AKs");
+        assert_eq!(classes[13].to_string(), "AKo");
+        assert_eq!(class("t9S"), HandClass::new(Rank::Nine, Rank::Ten, true));
+        assert_eq!(HandClass::of(&[TWO_CLUBS, SEVEN_HEARTS]).to_string(), "72o");
+        assert!("AA s".parse::<HandClass>().is_err());
+        assert!("AAs".parse::<HandClass>().is_err());
+        assert!("AK".parse::<HandClass>().is_err());
+        assert!("AKsx".parse::<HandClass>().is_err());
+    }
+
+    #[test]
+    fn matchups_are_weighted_by_combos() {
+        // Kings share no suits with the aces, one suit, or both suits
+        let matchups = canonical_matchups(class("AA"), class("KK"));
+        let mut weights: Vec<u32> = matchups.iter().map(|m| m.2).collect();
+        weights.sort_unstable();
+        assert_eq!(weights, vec![6, 6, 24]);
+        let total = |a: &str, b: &str| canonical_matchups(class(a), class(b)).iter().map(|m| m.2).sum::<u32>();
+        assert_eq!(total("AKs", "AKs"), 12);
+        // Each AKo combo holds one of the aces, leaving three AA combos
+        assert_eq!(total("AKo", "AA"), 36);
+        assert_eq!(total("72o", "AKs"), 48);
+    }
+
+    #[test]
+    fn sampled_class_equity() {
+        let mode = EquityMode::MonteCarlo(Simulation::new(2000).seed(3));
+        let equity = class_vs_class(class("AA"), class("KK"), mode).unwrap();
+        assert!((equity.equity - 0.82).abs() < 0.03);
+    }
+
+    #[test]
+    fn table_lookups_and_round_trip() {
+        let mut table = PreflopTable{entries: vec![[0.0; 2]; NUM_CLASSES * NUM_CLASSES]};
+        table.set(class("AA"), class("KK"), &PlayerEquity{pwin: 0.8, ptie: 0.01, equity: 0.805, std_error: 0.0});
+        table.set(class("AA"), class("AKs"), &PlayerEquity{pwin: 0.85, ptie: 0.02, equity: 0.86, std_error: 0.0});
+        let kings = table.hand_vs_hand(&[KING_SPADES, KING_CLUBS], &[ACE_HEARTS, ACE_DIAMONDS]);
+        assert!((kings.pwin - 0.19).abs() < 1e-6);
+        assert!((kings.equity - 0.195).abs() < 1e-6);
+
+        // AsKs and AcKc are blocked, leaving six KK combos and two AKs
+        let mut villain = Range::new();
+        for combo in class("KK").combos().into_iter().chain(class("AKs").combos()) {
+            villain.add(combo, 1.0);
+        }
+        let equity = table.hand_vs_range(&[ACE_SPADES, ACE_CLUBS], &villain).unwrap();
+        assert!((equity.equity - (6.0 * 0.805 + 2.0 * 0.86) / 8.0).abs() < 1e-6);
+
+        let mut bytes = Vec::new();
+        table.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 5 + NUM_CLASSES * NUM_CLASSES * 8);
+        assert_eq!(PreflopTable::read_from(&bytes[..]).unwrap(), table);
+        assert!(PreflopTable::read_from(&b"not a table"[..]).is_err());
+    }
+
+    #[test]
+    fn threads_share_the_matchups() {
+        // Seeded sampling gives every matchup the same result on any thread
+        let mode = EquityMode::MonteCarlo(Simulation::new(1).seed(5));
+        let table = PreflopTable::generate_with_threads(mode, 3);
+        for (a, b) in [("AA", "72o"), ("KQs", "JTs")] {
+            let equity = class_vs_class(class(a), class(b), mode).unwrap();
+            let [pwin, ptie] = [equity.pwin as f32, equity.ptie as f32];
+            assert_eq!(table.entries[class(a).index() * NUM_CLASSES + class(b).index()], [pwin, ptie]);
+        }
+        let aces = table.class_equity(class("AA"), class("72o"));
+        let sevens = table.class_equity(class("72o"), class("AA"));
+        assert!((aces.pwin + aces.ptie + sevens.pwin - 1.0).abs() < 1e-6);
+        let deuces = table.class_equity(class("22"), class("22"));
+        assert!((2.0 * deuces.pwin + deuces.ptie - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn a_class_against_itself_is_even() {
+        let mut table = PreflopTable{entries: vec![[0.0; 2]; NUM_CLASSES * NUM_CLASSES]};
+        // A sampled estimate that happens to favour one side
+        table.set(class("AKs"), class("AKs"), &PlayerEquity{pwin: 0.1, ptie: 0.8, equity: 0.5, std_error: 0.0});
+        let equity = table.class_equity(class("AKs"), class("AKs"));
+        assert!((equity.pwin - 0.1).abs() < 1e-6);
+        assert!((equity.ptie - 0.8).abs() < 1e-6);
+        table.set(class("AKs"), class("AKs"), &PlayerEquity{pwin: 0.2, ptie: 0.7, equity: 0.55, std_error: 0.0});
+        let equity = table.class_equity(class("AKs"), class("AKs"));
+        assert!((equity.pwin - 0.15).abs() < 1e-6);
+        assert!((equity.equity - 0.5).abs() < 1e-6);
+    }
+}
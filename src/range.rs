@@ -0,0 +1,70 @@
+//! Weighted ranges of hole cards
+
+use crate::card::*;
+use crate::util::*;
+
+/// A single combo in a range and the relative frequency it is held with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightedCombo {
+    pub cards: HoleCards,
+    pub weight: f64,
+}
+
+/// A range of hole card combos, each with a weight.
+///
+/// Weights are relative: a combo with weight 1.0 is held twice as often as a
+/// combo with weight 0.5, and a range need not sum to any particular total.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::range::*;
+///
+/// let mut range = Range::new();
+/// range.add([ACE_SPADES, ACE_CLUBS], 1.0);
+/// range.add([KING_SPADES, KING_CLUBS], 0.5);
+/// assert_eq!(range.total_weight(), 1.5);
+/// assert_eq!(range.without_blocked(&[ACE_SPADES]).combos().len(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Range {
+    combos: Vec<WeightedCombo>,
+}
+
+impl Range {
+    /// Return an empty range
+    pub fn new() -> Range {
+        Range::default()
+    }
+
+    /// Return a range holding every combo in `combos`
+    pub fn from_combos(combos: Vec<WeightedCombo>) -> Range {
+        Range{combos}
+    }
+
+    /// Add `cards` to the range with the given weight
+    pub fn add(&mut self, cards: HoleCards, weight: f64) {
+        self.combos.push(WeightedCombo{cards, weight});
+    }
+
+    /// Every combo in the range
+    pub fn combos(&self) -> &[WeightedCombo] {
+        &self.combos
+    }
+
+    /// Sum of the weights of every combo in the range
+    pub fn total_weight(&self) -> f64 {
+        self.combos.iter().map(|combo| combo.weight).sum()
+    }
+
+    /// Return a copy of the range without any combo that shares a card with
+    /// `cards` or has no weight.
+    pub fn without_blocked(&self, cards: &[Card]) -> Range {
+        Range{
+            combos: self.combos.iter()
+                .filter(|combo| combo.weight > 0.0 && !cards_overlap(&combo.cards, cards))
+                .cloned()
+                .collect()
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Run the rest of the board more than once
+
+use crate::card::*;
+use crate::equity::*;
+use crate::showdown::*;
+use crate::simulation::Merge;
+use crate::util::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// The distribution of one player's share of the pot when the board is run
+/// several times
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareDistribution {
+    /// Every share of the pot the player can end up with and its probability,
+    /// from the smallest share to the largest
+    pub outcomes: Vec<(f64, f64)>,
+    /// Expected share of the pot, which is the player's equity however many
+    /// times the board is run
+    pub mean: f64,
+    /// Variance of the player's share of the pot
+    pub variance: f64,
+    /// Variance of the player's share of the pot if the board were run once
+    pub single_run_variance: f64,
+}
+
+impl ShareDistribution {
+    /// Probability that the player ends up with exactly `share` of the pot
+    pub fn probability(&self, share: f64) -> f64 {
+        self.outcomes.iter().find(|outcome| (outcome.0 - share).abs() < 1e-9).map_or(0.0, |outcome| outcome.1)
+    }
+
+    /// Standard deviation of the player's share of the pot
+    pub fn std_dev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    /// Fraction of the single run variance removed by running the board
+    /// several times. Zero when a single run has no variance to remove.
+    pub fn variance_reduction(&self) -> f64 {
+        if self.single_run_variance == 0.0 {
+            0.0
+        } else {
+            1.0 - self.variance / self.single_run_variance
+        }
+    }
+}
+
+/// The outcome of running the board several times
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunItTimes {
+    /// Number of times the board is run
+    pub runs: usize,
+    /// Each player's share distribution, in the order the hands were given
+    pub players: Vec<ShareDistribution>,
+    /// Number of sets of runs shown down
+    pub trials: u64,
+    /// Wall clock time spent on the calculation
+    pub elapsed: Duration,
+}
+
+/// Compute the distribution of each player's share of the pot when the rest of
+/// the board is dealt `runs` times, with each run taking half, a third and so on
+/// of the pot. Cards in `dead` are never dealt.
+///
+/// Each run is dealt from what is left of the deck after the runs before it,
+/// so no card appears on two runs. Running the board more than once leaves
+/// every player's equity unchanged but narrows the spread of outcomes.
+///
+/// In `EquityMode::Exact` every ordered set of runs is shown down, which is
+/// feasible for running the turn or river several times, or the flop twice
+/// heads up.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::EquityMode;
+/// use rusty_poker::run_it::*;
+///
+/// // An open-ended straight draw with eight outs runs the river twice
+/// let board = [NINE_CLUBS, EIGHT_DIAMONDS, TWO_SPADES, FOUR_HEARTS];
+/// let hands = [[JACK_HEARTS, TEN_HEARTS], [ACE_HEARTS, ACE_DIAMONDS]];
+/// let result = run_it_times(&hands, &board, &[], 2, EquityMode::Exact).unwrap();
+/// let draw = &result.players[0];
+/// assert!((draw.mean - 8.0 / 44.0).abs() < 1e-12);
+/// assert!((draw.probability(1.0) - 8.0 * 7.0 / (44.0 * 43.0)).abs() < 1e-12);
+/// // Running it twice removes a little over half the variance
+/// assert!(draw.variance_reduction() > 0.5);
+/// ```
+pub fn run_it_times(hands: &[HoleCards], board: &[Card], dead: &[Card], runs: usize, mode: EquityMode) -> Result<RunItTimes, EquityError> {
+    if hands.is_empty() {
+        return Err(EquityError::NoPlayers);
+    }
+    let start = Instant::now();
+    let deck = live_deck(hands, board, dead)?;
+    check_trials(mode)?;
+    let needed = 5 - board.len();
+    if runs == 0 || runs * needed > deck.len() {
+        return Err(EquityError::InvalidRuns(runs));
+    }
+    let init = || RunTally::new(hands.len(), runs);
+    let record = |tally: &mut RunTally, boards: &[Vec<Card>]| {
+        let results: Vec<Showdown> = boards.iter().map(|full_board| showdown(hands, full_board)).collect();
+        tally.record(&results);
+    };
+
+    let tally = match mode {
+        EquityMode::Exact => {
+            let mut tally = init();
+            for_each_board_deal(&deck, &vec![board.to_vec(); runs], |boards| record(&mut tally, boards));
+            tally
+        }
+        _ => sample(mode, init, |rng: &mut StdRng, tally: &mut RunTally| {
+            let dealt: Vec<Card> = deck.choose_multiple(rng, runs * needed).copied().collect();
+            let boards: Vec<Vec<Card>> = (0..runs).map(|run| [board, &dealt[run * needed..(run + 1) * needed]].concat()).collect();
+            record(tally, &boards);
+        }, |_| true),
+    };
+
+    Ok(RunItTimes{runs, players: tally.distributions(), trials: tally.trials, elapsed: start.elapsed()})
+}
+
+/// A share of the pot as a fraction in lowest terms, numerator first, so equal
+/// shares always land in the same bucket
+type Share = (u64, u64);
+
+/// Running counts of each player's share of the pot
+struct RunTally {
+    runs: usize,
+    trials: u64,
+    /// How often each player ends up with each share of the pot over all runs
+    totals: Vec<HashMap<Share, u64>>,
+    /// How often each player takes a single run, counting every run and
+    /// indexed by the number of players the run is split between, with zero
+    /// for runs the player loses
+    single: Vec<Vec<u64>>,
+}
+
+impl RunTally {
+    fn new(num_players: usize, runs: usize) -> RunTally {
+        RunTally {
+            runs,
+            trials: 0,
+            totals: vec![HashMap::new(); num_players],
+            single: vec![vec![0; num_players + 1]; num_players],
+        }
+    }
+
+    fn record(&mut self, results: &[Showdown]) {
+        let mut shares: Vec<Share> = vec![(0, 1); self.totals.len()];
+        for result in results {
+            let winners = result.winners().len();
+            for &p in result.winners() {
+                shares[p] = add_shares(shares[p], (1, winners as u64));
+                self.single[p][winners] += 1;
+            }
+            for (p, single) in self.single.iter_mut().enumerate() {
+                if !result.winners().contains(&p) {
+                    single[0] += 1;
+                }
+            }
+        }
+        for (p, (num, den)) in shares.into_iter().enumerate() {
+            let share = reduce(num, den * self.runs as u64);
+            *self.totals[p].entry(share).or_insert(0) += 1;
+        }
+        self.trials += 1;
+    }
+
+    fn distributions(&self) -> Vec<ShareDistribution> {
+        self.totals.iter().zip(&self.single).map(|(totals, single)| {
+            let (mean, variance) = moments(totals.iter().map(|(&(num, den), &count)| (num as f64 / den as f64, count)));
+            let mut outcomes: Vec<(f64, f64)> = totals.iter()
+                .map(|(&(num, den), &count)| (num as f64 / den as f64, count as f64 / self.trials as f64))
+                .collect();
+            outcomes.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let single_run = single.iter().enumerate()
+                .map(|(winners, &count)| (if winners == 0 { 0.0 } else { 1.0 / winners as f64 }, count));
+            ShareDistribution{outcomes, mean, variance, single_run_variance: moments(single_run).1}
+        }).collect()
+    }
+}
+
+/// Mean and variance of the shares counted in `counts`, given as each share
+/// with the number of times it came up
+fn moments<I: Iterator<Item = (f64, u64)> + Clone>(counts: I) -> (f64, f64) {
+    let n: u64 = counts.clone().map(|(_, c)| c).sum();
+    if n == 0 {
+        return (0.0, 0.0);
+    }
+    let mean = counts.clone().map(|(share, c)| share * c as f64).sum::<f64>() / n as f64;
+    let variance = counts.map(|(share, c)| (share - mean).powi(2) * c as f64).sum::<f64>() / n as f64;
+    (mean, variance)
+}
+
+impl Merge for RunTally {
+    fn merge(&mut self, other: RunTally) {
+        self.trials += other.trials;
+        for (mine, theirs) in self.totals.iter_mut().zip(other.totals) {
+            for (share, count) in theirs {
+                *mine.entry(share).or_insert(0) += count;
+            }
+        }
+        for (mine, theirs) in self.single.iter_mut().zip(other.single) {
+            for (a, b) in mine.iter_mut().zip(theirs) {
+                *a += b;
+            }
+        }
+    }
+}
+
+impl Spread for RunTally {
+    fn max_std_error(&self) -> f64 {
+        self.totals.iter()
+            .map(|totals| moments(totals.iter().map(|(&(num, den), &count)| (num as f64 / den as f64, count))).1)
+            .map(|variance| (variance / self.trials.max(1) as f64).sqrt())
+            .fold(0.0, f64::max)
+    }
+}
+
+fn add_shares(a: Share, b: Share) -> Share {
+    reduce(a.0 * b.1 + b.0 * a.1, a.1 * b.1)
+}
+
+fn reduce(num: u64, den: u64) -> Share {
+    let divisor = gcd(num, den);
+    (num / divisor, den / divisor)
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        let t = x % y;
+        x = y;
+        y = t;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    use crate::simulation::Simulation;
+    use super::*;
+
+    #[test]
+    fn running_the_river_twice() {
+        let board = [NINE_CLUBS, EIGHT_DIAMONDS, TWO_SPADES, FOUR_HEARTS];
+        let hands = [[JACK_HEARTS, TEN_HEARTS], [ACE_HEARTS, ACE_DIAMONDS]];
+        let result = run_it_times(&hands, &board, &[], 2, EquityMode::Exact).unwrap();
+        assert_eq!(result.trials, 44 * 43);
+        let (draw, aces) = (&result.players[0], &result.players[1]);
+        let orders = 44.0 * 43.0;
+        assert_eq!(draw.outcomes.len(), 3);
+        assert!((draw.probability(0.0) - 36.0 * 35.0 / orders).abs() < 1e-12);
+        assert!((draw.probability(0.5) - 2.0 * 8.0 * 36.0 / orders).abs() < 1e-12);
+        assert!((aces.probability(1.0) - draw.probability(0.0)).abs() < 1e-12);
+
+        // One run has the variance of a single coin flip at the draw's equity
+        let p = 8.0 / 44.0;
+        assert!((draw.single_run_variance - p * (1.0 - p)).abs() < 1e-12);
+        // Without replacement the two runs are negatively correlated, so
+        // slightly more than half the variance goes away
+        let expected = 0.5 * p * (1.0 - p) * (1.0 - 1.0 / 43.0);
+        assert!((draw.variance - expected).abs() < 1e-12);
+        assert!(draw.variance_reduction() > 0.5);
+    }
+
+    #[test]
+    fn one_run_matches_equity() {
+        let board = [NINE_CLUBS, EIGHT_DIAMONDS, TWO_SPADES, FOUR_HEARTS];
+        let hands = [[JACK_HEARTS, TEN_HEARTS], [ACE_HEARTS, ACE_DIAMONDS], [NINE_SPADES, EIGHT_SPADES]];
+        let once = run_it_times(&hands, &board, &[], 1, EquityMode::Exact).unwrap();
+        let equity = multiway_equity(&hands, &board, &[], EquityMode::Exact).unwrap();
+        for (player, equity) in once.players.iter().zip(&equity.players) {
+            assert!((player.mean - equity.equity).abs() < 1e-12);
+            assert!((player.variance - player.single_run_variance).abs() < 1e-12);
+            assert_eq!(player.variance_reduction(), 0.0);
+        }
+    }
+
+    #[test]
+    fn chopped_runs_in_a_multiway_pot() {
+        // Two broadway straights chop, a ten on the river gives the aces
+        // broadway too and anything that pairs the board fills up the aces
+        let board = [ACE_SPADES, KING_DIAMONDS, QUEEN_CLUBS, JACK_HEARTS];
+        let hands = [[TEN_HEARTS, THREE_CLUBS], [TEN_SPADES, FOUR_DIAMONDS], [ACE_CLUBS, ACE_DIAMONDS]];
+        let result = run_it_times(&hands, &board, &[], 2, EquityMode::Exact).unwrap();
+        let orders = 42.0 * 41.0;
+        let straight = &result.players[0];
+        assert_eq!(straight.outcomes.len(), 6);
+        for (share, ways) in [(0.0, 10.0 * 9.0), (1.0 / 6.0, 2.0 * 10.0 * 2.0), (0.25, 2.0 * 10.0 * 30.0), (1.0 / 3.0, 2.0), (5.0 / 12.0, 2.0 * 2.0 * 30.0), (0.5, 30.0 * 29.0)] {
+            assert!((straight.probability(share) - ways / orders).abs() < 1e-12);
+        }
+        assert!((result.players[2].probability(1.0) - 10.0 * 9.0 / orders).abs() < 1e-12);
+        // A single run gives nothing, a third or half the pot
+        let mean = (2.0 / 3.0 + 15.0) / 42.0;
+        let square = (2.0 / 9.0 + 30.0 / 4.0) / 42.0;
+        assert!((straight.single_run_variance - (square - mean * mean)).abs() < 1e-12);
+
+        // Twenty players chop in many different ways without running out of
+        // memory
+        let deck = all_cards();
+        let hands: Vec<HoleCards> = (0..20).map(|i| [deck[2 * i], deck[2 * i + 1]]).collect();
+        let sim = Simulation::new(2000).seed(12);
+        let result = run_it_times(&hands, &[], &[], 2, EquityMode::MonteCarlo(sim)).unwrap();
+        assert!((result.players.iter().map(|player| player.mean).sum::<f64>() - 1.0).abs() < 1e-9);
+        for player in &result.players {
+            assert!((player.outcomes.iter().map(|outcome| outcome.1).sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn sampled_runs_from_preflop() {
+        let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS]];
+        let sim = Simulation::new(20_000).seed(11);
+        let result = run_it_times(&hands, &[], &[], 3, EquityMode::MonteCarlo(sim)).unwrap();
+        assert_eq!(result.trials, 20_000);
+        let aces = &result.players[0];
+        assert!((aces.outcomes.iter().map(|outcome| outcome.1).sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!((aces.mean - 0.82).abs() < 0.02);
+        assert!((aces.mean + result.players[1].mean - 1.0).abs() < 1e-9);
+        assert!(aces.variance_reduction() > 0.6);
+    }
+
+    #[test]
+    fn rejects_impossible_runs() {
+        let hands = [[ACE_SPADES, ACE_CLUBS], [KING_SPADES, KING_CLUBS]];
+        assert_eq!(run_it_times(&hands, &[], &[], 0, EquityMode::Exact).unwrap_err(), EquityError::InvalidRuns(0));
+        // Ten runs of five cards need more than the 48 cards left
+        assert_eq!(run_it_times(&hands, &[], &[], 10, EquityMode::Exact).unwrap_err(), EquityError::InvalidRuns(10));
+
+        // A complete board gives the same result on every run
+        let river = [KING_HEARTS, SEVEN_DIAMONDS, TWO_HEARTS, FOUR_CLUBS, NINE_DIAMONDS];
+        let result = run_it_times(&hands, &river, &[], 3, EquityMode::Exact).unwrap();
+        assert_eq!(result.players[1].outcomes, vec![(1.0, 1.0)]);
+        assert_eq!(result.players[1].variance_reduction(), 0.0);
+    }
+}
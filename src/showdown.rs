@@ -0,0 +1,91 @@
+//! Resolve a showdown between any number of players
+
+use crate::card::*;
+use crate::hand_type::*;
+use std::cmp::{Ordering, Reverse};
+
+/// The result of showing down a set of hands on a board
+#[derive(Debug, Clone)]
+pub struct Showdown {
+    /// Each player's best hand, in the order the players were given
+    pub hands: Vec<HandType>,
+    /// Player indices grouped by hand strength, best group first. Players in
+    /// the same group tie with each other.
+    pub ranking: Vec<Vec<usize>>,
+}
+
+impl Showdown {
+    /// Indices of the players who win or split the pot
+    pub fn winners(&self) -> &[usize] {
+        &self.ranking[0]
+    }
+
+    /// True if more than one player shares the pot
+    pub fn is_split(&self) -> bool {
+        self.ranking[0].len() > 1
+    }
+
+    /// Each player's share of the pot, in the order the players were given
+    pub fn shares(&self) -> Vec<f64> {
+        let mut shares = vec![0.0; self.hands.len()];
+        let share = 1.0 / self.winners().len() as f64;
+        for &i in self.winners() {
+            shares[i] = share;
+        }
+        shares
+    }
+}
+
+/// Show down every player's hole cards on `board`.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::showdown::*;
+///
+/// let board = vec![ACE_SPADES, KING_SPADES, QUEEN_HEARTS, JACK_DIAMONDS, TEN_CLUBS];
+/// let result = showdown(&[[TWO_CLUBS, THREE_CLUBS], [ACE_HEARTS, ACE_DIAMONDS], [FOUR_CLUBS, FIVE_HEARTS]], &board);
+/// // Everyone plays the broadway straight on the board
+/// assert_eq!(result.winners(), &[0, 1, 2]);
+/// ```
+pub fn showdown(players: &[HoleCards], board: &[Card]) -> Showdown {
+    assert!(!players.is_empty(), "A showdown needs at least one player");
+    let hands: Vec<HandType> = players.iter().map(|hole_cards| hand_type(hole_cards, board)).collect();
+
+    let mut order: Vec<usize> = (0..players.len()).collect();
+    order.sort_by_key(|&i| Reverse(hands[i]));
+    let mut ranking: Vec<Vec<usize>> = Vec::new();
+    for i in order {
+        match ranking.last_mut() {
+            Some(group) if hands[group[0]].cmp(&hands[i]) == Ordering::Equal => group.push(i),
+            _ => ranking.push(vec![i]),
+        }
+    }
+    Showdown{hands, ranking}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    use super::*;
+
+    #[test]
+    fn single_winner() {
+        let board = [ACE_SPADES, KING_SPADES, SEVEN_HEARTS, FOUR_DIAMONDS, TWO_CLUBS];
+        let result = showdown(&[[KING_CLUBS, QUEEN_CLUBS], [ACE_HEARTS, ACE_DIAMONDS], [SEVEN_CLUBS, FOUR_CLUBS]], &board);
+        assert_eq!(result.winners(), &[1]);
+        assert!(!result.is_split());
+        assert_eq!(result.ranking, vec![vec![1], vec![2], vec![0]]);
+        assert_eq!(result.hands[1].category(), HandCategory::Trips);
+    }
+
+    #[test]
+    fn split_among_some_players() {
+        let board = [ACE_SPADES, KING_SPADES, SEVEN_HEARTS, FOUR_DIAMONDS, TWO_CLUBS];
+        let players = [[ACE_CLUBS, QUEEN_CLUBS], [TWO_HEARTS, THREE_HEARTS], [ACE_HEARTS, QUEEN_DIAMONDS], [ACE_DIAMONDS, JACK_DIAMONDS]];
+        let result = showdown(&players, &board);
+        assert!(result.is_split());
+        assert_eq!(result.ranking, vec![vec![0, 2], vec![3], vec![1]]);
+        assert_eq!(result.shares(), vec![0.5, 0.0, 0.5, 0.0]);
+    }
+}
@@ -0,0 +1,295 @@
+//! Run Monte Carlo trials across worker threads with reproducible seeding
+
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Simulations with fewer trials than this gain too little from extra threads
+/// to pay for starting them, so `Simulation::all_cores` keeps them on one
+pub const MIN_PARALLEL_TRIALS: u64 = 50_000;
+
+/// Settings for a Monte Carlo simulation.
+///
+/// Trials are split as evenly as possible across `threads` workers. Each worker
+/// draws from its own random stream derived from the master seed and the
+/// worker's index, so a given `(seed, threads)` pair always produces the same
+/// results.
+///
+/// # Example
+/// ```
+/// use rusty_poker::simulation::*;
+///
+/// let sim = Simulation::new(10_000).threads(4).seed(42);
+/// assert_eq!(sim.trials, 10_000);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Simulation {
+    /// Total number of trials across all threads
+    pub trials: u64,
+    /// Number of worker threads
+    pub threads: usize,
+    /// Master seed, or `None` to pick one at random
+    pub seed: Option<u64>,
+}
+
+impl Simulation {
+    /// A single threaded, randomly seeded simulation of `trials` trials
+    pub fn new(trials: u64) -> Simulation {
+        Simulation{trials, threads: 1, seed: None}
+    }
+
+    /// Split the trials across `threads` worker threads
+    pub fn threads(mut self, threads: usize) -> Simulation {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Split the trials across every available core, or keep them on one
+    /// thread if there are fewer than `MIN_PARALLEL_TRIALS` of them
+    pub fn all_cores(self) -> Simulation {
+        if self.trials < MIN_PARALLEL_TRIALS {
+            return self.threads(1);
+        }
+        self.threads(thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    /// Seed every worker from `seed` so results are reproducible
+    pub fn seed(mut self, seed: u64) -> Simulation {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// A flag that asks a running calculation to stop early.
+///
+/// Clones share the same flag, so one clone can be handed to the calculation
+/// and another kept by whoever may want to cancel it, possibly on another
+/// thread.
+///
+/// # Example
+/// ```
+/// use rusty_poker::simulation::CancelToken;
+///
+/// let token = CancelToken::new();
+/// let handle = token.clone();
+/// handle.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Ask every calculation watching this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Run `sim.trials` calls to `trial` across `sim.threads` threads.
+///
+/// Each worker starts from its own accumulator built by `init` and passes it,
+/// together with the worker's random number generator, to every call to
+/// `trial`. No state is shared between workers while they run. The finished
+/// accumulators are returned in worker order so the caller can merge them.
+/// A single worker runs on the calling thread.
+pub fn simulate<A, I, T>(sim: &Simulation, init: I, trial: T) -> Vec<A>
+where
+    A: Send,
+    I: Fn() -> A + Sync,
+    T: Fn(&mut StdRng, &mut A) + Sync,
+{
+    let master = sim.seed.unwrap_or_else(|| thread_rng().gen());
+    let threads = sim.threads.max(1);
+    let (init, trial) = (&init, &trial);
+    let run = move |worker: usize| {
+        let mut rng = StdRng::seed_from_u64(worker_seed(master, worker));
+        let mut acc = init();
+        for _ in 0..worker_trials(sim.trials, threads, worker) {
+            trial(&mut rng, &mut acc);
+        }
+        acc
+    };
+    if threads == 1 {
+        return vec![run(0)];
+    }
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|worker| scope.spawn(move || run(worker))).collect();
+        workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+    })
+}
+
+/// Run trials in batches of `batch` until `stop` returns true or `sim.trials`
+/// trials have been run, whichever comes first.
+///
+/// The `sim.threads` workers are started once and each batch is split across
+/// them as `simulate` splits its trials. Every worker's results for a batch
+/// are merged into the running total, and `stop` is called after every batch
+/// with the running total and the time elapsed so far. Each worker draws from
+/// the same random stream for the whole run, so seeded runs that stop after
+/// the same number of batches are reproducible.
+pub fn simulate_until<A, I, T, S>(sim: &Simulation, batch: u64, init: I, trial: T, mut stop: S) -> A
+where
+    A: Merge + Send,
+    I: Fn() -> A + Sync,
+    T: Fn(&mut StdRng, &mut A) + Sync,
+    S: FnMut(&A, Duration) -> bool,
+{
+    let start = Instant::now();
+    let master = sim.seed.unwrap_or_else(|| thread_rng().gen());
+    let threads = sim.threads.max(1);
+    let batch = batch.max(1);
+    let (init, trial) = (&init, &trial);
+    let run_batch = |rng: &mut StdRng, trials: u64| {
+        let mut acc = init();
+        for _ in 0..trials {
+            trial(rng, &mut acc);
+        }
+        acc
+    };
+    let mut total = init();
+    let mut run_batches = |next_batch: &mut dyn FnMut(u64) -> A| {
+        let mut trials = 0;
+        while trials < sim.trials {
+            let round = batch.min(sim.trials - trials);
+            total.merge(next_batch(round));
+            trials += round;
+            if stop(&total, start.elapsed()) {
+                break;
+            }
+        }
+    };
+
+    if threads == 1 {
+        let mut rng = StdRng::seed_from_u64(worker_seed(master, 0));
+        run_batches(&mut |trials| run_batch(&mut rng, trials));
+        return total;
+    }
+    thread::scope(|scope| {
+        // Each worker waits for the size of its next share of a batch and
+        // sends back the accumulator for it, until its orders are dropped
+        let workers: Vec<(Sender<u64>, Receiver<A>)> = (0..threads)
+            .map(|worker| {
+                let (order, orders) = mpsc::channel();
+                let (result, results) = mpsc::channel();
+                scope.spawn(move || {
+                    let mut rng = StdRng::seed_from_u64(worker_seed(master, worker));
+                    for trials in orders {
+                        if result.send(run_batch(&mut rng, trials)).is_err() {
+                            break;
+                        }
+                    }
+                });
+                (order, results)
+            })
+            .collect();
+        run_batches(&mut |trials| {
+            for (worker, (order, _)) in workers.iter().enumerate() {
+                order.send(worker_trials(trials, threads, worker)).expect("Worker thread stopped");
+            }
+            merged(workers.iter().map(|(_, results)| results.recv().expect("Worker thread panicked")).collect())
+        });
+    });
+    total
+}
+/// Running totals that can be combined across worker threads
+pub trait Merge {
+    /// Add `other`'s totals into `self`
+    fn merge(&mut self, other: Self);
+}
+
+impl<T: Merge> Merge for Vec<T> {
+    fn merge(&mut self, other: Vec<T>) {
+        for (mine, theirs) in self.iter_mut().zip(other) {
+            mine.merge(theirs);
+        }
+    }
+}
+
+/// Merge every worker's totals into the first worker's
+pub fn merged<A: Merge>(accs: Vec<A>) -> A {
+    let mut accs = accs.into_iter();
+    let mut total = accs.next().expect("No totals to merge");
+    for acc in accs {
+        total.merge(acc);
+    }
+    total
+}
+
+/// Number of the `trials` that `worker` runs when they are split as evenly as
+/// possible across `threads` workers
+fn worker_trials(trials: u64, threads: usize, worker: usize) -> u64 {
+    trials / threads as u64 + if (worker as u64) < trials % threads as u64 { 1 } else { 0 }
+}
+
+/// Derive a worker's seed from the master seed with the SplitMix64 mixer, so
+/// neighbouring workers get unrelated streams.
+fn worker_seed(master: u64, worker: usize) -> u64 {
+    let mut z = master.wrapping_add((worker as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sum_of_draws(sim: &Simulation) -> Vec<(u64, u64)> {
+        simulate(sim, || (0u64, 0u64), |rng, acc| {
+            acc.0 += 1;
+            acc.1 = acc.1.wrapping_add(rng.gen::<u64>());
+        })
+    }
+
+    #[test]
+    fn trials_are_split_across_threads() {
+        let results = sum_of_draws(&Simulation::new(10).threads(3));
+        assert_eq!(results.iter().map(|r| r.0).collect::<Vec<_>>(), vec![4, 3, 3]);
+    }
+
+    struct Count(u64);
+
+    impl Merge for Count {
+        fn merge(&mut self, other: Count) {
+            self.0 += other.0;
+        }
+    }
+
+    #[test]
+    fn simulate_until_stops_early() {
+        let sim = Simulation::new(1000).threads(2);
+        let count = simulate_until(&sim, 100, || Count(0), |_, count| count.0 += 1, |count, _| count.0 >= 300);
+        assert_eq!(count.0, 300);
+        let count = simulate_until(&sim, 400, || Count(0), |_, count| count.0 += 1, |_, _| false);
+        assert_eq!(count.0, 1000);
+    }
+
+    #[test]
+    fn small_simulations_stay_on_one_thread() {
+        assert_eq!(Simulation::new(MIN_PARALLEL_TRIALS - 1).all_cores().threads, 1);
+        let cores = thread::available_parallelism().map_or(1, |n| n.get());
+        assert_eq!(Simulation::new(MIN_PARALLEL_TRIALS).all_cores().threads, cores);
+    }
+
+    #[test]
+    fn seeded_runs_are_reproducible() {
+        let sim = Simulation::new(1000).threads(4).seed(7);
+        assert_eq!(sum_of_draws(&sim), sum_of_draws(&sim));
+        assert_ne!(sum_of_draws(&sim), sum_of_draws(&sim.seed(8)));
+        let until = |sim: &Simulation| simulate_until(sim, 300, || Count(0), |rng, count| count.0 += rng.gen::<u32>() as u64, |_, _| false).0;
+        assert_eq!(until(&sim), until(&sim));
+        assert_ne!(until(&sim), until(&sim.seed(8)));
+    }
+}
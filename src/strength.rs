@@ -0,0 +1,290 @@
+//! Hand strength and hand potential, as used by poker bots
+//!
+//! These are the metrics introduced by Billings et al. for the Loki and Poki
+//! bots. Hand strength is the chance of holding the best hand against a
+//! random holding right now. Positive potential is the chance that a hand
+//! behind now ends up ahead by the river, and negative potential the chance
+//! that a hand ahead now ends up behind. Effective hand strength combines
+//! the two.
+
+use crate::card::*;
+use crate::equity::*;
+use crate::hand_type::*;
+use crate::simulation::Merge;
+use crate::util::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::cmp::Ordering;
+
+/// Indices into the ahead, tied and behind counts
+const AHEAD: usize = 0;
+const TIED: usize = 1;
+const BEHIND: usize = 2;
+
+/// Strength and potential of a hand against random opponent holdings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HandStrength {
+    /// Number of opponents the hand is measured against
+    pub opponents: usize,
+    /// Chance of holding the best hand against one random holding right now,
+    /// counting ties as half
+    pub hs: f64,
+    /// Hand strength against every opponent, approximated as `hs` raised to
+    /// the number of opponents
+    pub hs_n: f64,
+    /// Positive potential: the chance of ending up ahead at the river when
+    /// behind now, counting ties as half
+    pub ppot: f64,
+    /// Negative potential: the chance of ending up behind at the river when
+    /// ahead now, counting ties as half
+    pub npot: f64,
+    /// Effective hand strength, `hs_n * (1 - npot) + (1 - hs_n) * ppot`
+    pub ehs: f64,
+    /// Expected square of the hand strength against every opponent once the
+    /// board is complete, which rewards hands whose strength varies
+    pub ehs2: f64,
+}
+
+/// Running counts behind a `HandStrength`
+#[derive(Debug, Clone, Copy, Default)]
+struct StrengthTally {
+    /// Opponent holdings by whether hero is ahead, tied or behind now
+    now: [u64; 3],
+    /// Opponent holdings and runouts by hero's position now and at the river
+    potential: [[u64; 3]; 3],
+    /// Sum over runouts of the squared river hand strength
+    squared: f64,
+    runouts: u64,
+}
+
+impl StrengthTally {
+    fn hs(&self) -> f64 {
+        let total: u64 = self.now.iter().sum();
+        (self.now[AHEAD] as f64 + self.now[TIED] as f64 / 2.0) / total as f64
+    }
+
+    fn strength(&self, opponents: usize) -> HandStrength {
+        let hp = |now: usize, river: usize| self.potential[now][river] as f64;
+        let total = |now: usize| self.potential[now].iter().sum::<u64>() as f64;
+        let ratio = |num: f64, den: f64| if den == 0.0 { 0.0 } else { num / den };
+        let ppot = ratio(hp(BEHIND, AHEAD) + hp(BEHIND, TIED) / 2.0 + hp(TIED, AHEAD) / 2.0, total(BEHIND) + total(TIED) / 2.0);
+        let npot = ratio(hp(AHEAD, BEHIND) + hp(TIED, BEHIND) / 2.0 + hp(AHEAD, TIED) / 2.0, total(AHEAD) + total(TIED) / 2.0);
+        let hs = self.hs();
+        let hs_n = hs.powi(opponents as i32);
+        HandStrength {
+            opponents,
+            hs,
+            hs_n,
+            ppot,
+            npot,
+            ehs: hs_n * (1.0 - npot) + (1.0 - hs_n) * ppot,
+            ehs2: self.squared / self.runouts as f64,
+        }
+    }
+}
+
+impl Merge for StrengthTally {
+    fn merge(&mut self, other: StrengthTally) {
+        for i in 0..3 {
+            self.now[i] += other.now[i];
+            for j in 0..3 {
+                self.potential[i][j] += other.potential[i][j];
+            }
+        }
+        self.squared += other.squared;
+        self.runouts += other.runouts;
+    }
+}
+
+impl Spread for StrengthTally {
+    fn max_std_error(&self) -> f64 {
+        let hs = self.hs();
+        (hs * (1.0 - hs) / self.runouts.max(1) as f64).sqrt()
+    }
+}
+
+/// Whether `hero` is ahead of, tied with or behind `villain`
+fn position(hero: &HandType, villain: &HandType) -> usize {
+    match hero.cmp(villain) {
+        Ordering::Greater => AHEAD,
+        Ordering::Equal => TIED,
+        Ordering::Less => BEHIND,
+    }
+}
+
+/// Hero's share against a single opponent: one for ahead, a half for tied
+fn score(position: usize) -> f64 {
+    match position {
+        AHEAD => 1.0,
+        TIED => 0.5,
+        _ => 0.0,
+    }
+}
+
+/// Measure the strength and potential of `hole` on `board` against
+/// `opponents` opponents holding random cards. Cards in `dead` are never
+/// held by an opponent or dealt.
+///
+/// The board must have between three and five cards. On the river there is
+/// nothing left to come, so both potentials are zero.
+///
+/// In `EquityMode::Exact` every opponent holding is played out on every
+/// runout. Sampling draws a runout and random opponent holdings for each
+/// trial, and estimates `ehs2` from independent pairs of holdings.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::EquityMode;
+/// use rusty_poker::strength::*;
+///
+/// // An open-ended straight draw on the turn is weak now but has potential
+/// let board = [QUEEN_HEARTS, JACK_DIAMONDS, FOUR_CLUBS, TWO_SPADES];
+/// let draw = hand_strength(&[KING_CLUBS, TEN_CLUBS], &board, &[], 1, EquityMode::Exact).unwrap();
+/// assert!(draw.hs < 0.6);
+/// assert!(draw.ppot > 0.2);
+/// assert!(draw.ehs > draw.hs);
+/// ```
+pub fn hand_strength(hole: &HoleCards, board: &[Card], dead: &[Card], opponents: usize, mode: EquityMode) -> Result<HandStrength, EquityError> {
+    if opponents == 0 {
+        return Err(EquityError::NoPlayers);
+    }
+    if board.len() < 3 || board.len() > 5 {
+        return Err(EquityError::InvalidBoard(board.len()));
+    }
+    let deck = live_deck(&[*hole], board, dead)?;
+    check_trials(mode)?;
+    let needed = 5 - board.len();
+    let hero_now = hand_type(hole, board);
+    // Each opponent holding with hero's position against it on the current
+    // board
+    let holdings: Vec<(HoleCards, usize)> = hole_card_combos(&deck).into_iter()
+        .map(|villain| (villain, position(&hero_now, &hand_type(&villain, board))))
+        .collect();
+    let doubled = 2 * opponents as i32;
+
+    let tally = match mode {
+        EquityMode::Exact => {
+            let mut tally = StrengthTally::default();
+            for &(_, now) in &holdings {
+                tally.now[now] += 1;
+            }
+            let mut full_board = board.to_vec();
+            for_each_combination(&deck, needed, |runout| {
+                full_board.truncate(board.len());
+                full_board.extend_from_slice(runout);
+                let hero_river = hand_type(hole, &full_board);
+                let mut river = [0u64; 3];
+                for (villain, now) in holdings.iter().filter(|(villain, _)| !cards_overlap(villain, runout)) {
+                    let later = position(&hero_river, &hand_type(villain, &full_board));
+                    tally.potential[*now][later] += 1;
+                    river[later] += 1;
+                }
+                let total: u64 = river.iter().sum();
+                let hs = (river[AHEAD] as f64 + river[TIED] as f64 / 2.0) / total as f64;
+                tally.squared += hs.powi(doubled);
+                tally.runouts += 1;
+            });
+            tally
+        }
+        _ => sample(mode, StrengthTally::default, |rng: &mut StdRng, tally: &mut StrengthTally| {
+            let mut full_board = board.to_vec();
+            full_board.extend(deck.choose_multiple(rng, needed));
+            let hero_river = hand_type(hole, &full_board);
+            let mut squared = 1.0;
+            for i in 0..doubled {
+                let (villain, now) = loop {
+                    let holding = holdings.choose(rng).unwrap();
+                    if !cards_overlap(&holding.0, &full_board[board.len()..]) {
+                        break holding;
+                    }
+                };
+                let later = position(&hero_river, &hand_type(villain, &full_board));
+                squared *= score(later);
+                if i == 0 {
+                    tally.now[*now] += 1;
+                    tally.potential[*now][later] += 1;
+                }
+            }
+            tally.squared += squared;
+            tally.runouts += 1;
+        }, |_| true),
+    };
+    Ok(tally.strength(opponents))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    use crate::simulation::Simulation;
+    use super::*;
+
+    const TURN: [Card; 4] = [QUEEN_HEARTS, JACK_DIAMONDS, FOUR_CLUBS, TWO_SPADES];
+
+    #[test]
+    fn river_has_no_potential() {
+        // Quad kings lose only to nothing on this board
+        let board = [KING_SPADES, KING_CLUBS, TWO_HEARTS, FOUR_CLUBS, NINE_DIAMONDS];
+        let quads = hand_strength(&[KING_HEARTS, KING_DIAMONDS], &board, &[], 3, EquityMode::Exact).unwrap();
+        assert_eq!(quads.hs, 1.0);
+        assert_eq!((quads.ppot, quads.npot), (0.0, 0.0));
+        assert_eq!(quads.ehs, 1.0);
+        assert_eq!(quads.ehs2, 1.0);
+
+        // Against more opponents the same hand is weaker
+        let pair = hand_strength(&[NINE_HEARTS, EIGHT_HEARTS], &board, &[], 1, EquityMode::Exact).unwrap();
+        let three = hand_strength(&[NINE_HEARTS, EIGHT_HEARTS], &board, &[], 3, EquityMode::Exact).unwrap();
+        assert_eq!(three.hs, pair.hs);
+        assert!((three.hs_n - pair.hs.powi(3)).abs() < 1e-12);
+        assert!((three.ehs2 - pair.hs.powi(6)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn turn_potentials() {
+        let draw = hand_strength(&[KING_CLUBS, TEN_CLUBS], &TURN, &[], 1, EquityMode::Exact).unwrap();
+        let overpair = hand_strength(&[ACE_HEARTS, ACE_DIAMONDS], &TURN, &[], 1, EquityMode::Exact).unwrap();
+        assert!(overpair.hs > draw.hs);
+        assert!(draw.ppot > overpair.ppot);
+        assert!(overpair.npot > 0.0 && overpair.npot < 0.2);
+        for strength in [draw, overpair] {
+            let ehs = strength.hs * (1.0 - strength.npot) + (1.0 - strength.hs) * strength.ppot;
+            assert!((strength.ehs - ehs).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn sampling_matches_enumeration() {
+        let exact = hand_strength(&[KING_CLUBS, TEN_CLUBS], &TURN, &[], 2, EquityMode::Exact).unwrap();
+        let sim = Simulation::new(40_000).seed(8);
+        let sampled = hand_strength(&[KING_CLUBS, TEN_CLUBS], &TURN, &[], 2, EquityMode::MonteCarlo(sim)).unwrap();
+        assert!((exact.hs - sampled.hs).abs() < 0.02);
+        assert!((exact.ppot - sampled.ppot).abs() < 0.03);
+        assert!((exact.ehs2 - sampled.ehs2).abs() < 0.02);
+    }
+
+    #[test]
+    fn flop_potentials() {
+        // With two cards to come a straight draw gains far more than bottom
+        // set, which is rarely behind and rarely caught
+        let flop = [JACK_HEARTS, TEN_DIAMONDS, THREE_CLUBS];
+        let draw = hand_strength(&[NINE_SPADES, EIGHT_SPADES], &flop, &[], 1, EquityMode::Exact).unwrap();
+        assert!(draw.ppot > 0.3);
+        assert!(draw.ehs > draw.hs);
+        let sim = Simulation::new(20_000).seed(2);
+        let sampled = hand_strength(&[NINE_SPADES, EIGHT_SPADES], &flop, &[], 1, EquityMode::MonteCarlo(sim)).unwrap();
+        assert!((draw.hs - sampled.hs).abs() < 0.02);
+        assert!((draw.ppot - sampled.ppot).abs() < 0.03);
+
+        let set = hand_strength(&[THREE_HEARTS, THREE_DIAMONDS], &flop, &[], 1, EquityMode::MonteCarlo(sim)).unwrap();
+        assert!(set.hs > 0.9 && draw.hs < set.hs);
+        assert!(draw.ppot > set.ppot);
+        assert!(set.npot < 0.2 && set.ehs < set.hs);
+    }
+
+    #[test]
+    fn rejects_bad_boards() {
+        assert_eq!(hand_strength(&[KING_CLUBS, TEN_CLUBS], &[], &[], 1, EquityMode::Exact).unwrap_err(), EquityError::InvalidBoard(0));
+        assert_eq!(hand_strength(&[KING_CLUBS, TEN_CLUBS], &TURN, &[], 0, EquityMode::Exact).unwrap_err(), EquityError::NoPlayers);
+        assert_eq!(hand_strength(&[QUEEN_HEARTS, TEN_CLUBS], &TURN, &[], 1, EquityMode::Exact).unwrap_err(), EquityError::DuplicateCard(QUEEN_HEARTS));
+    }
+}
@@ -0,0 +1,217 @@
+//! Describe the texture of a flop, turn or river board
+
+use crate::card::*;
+use crate::hand_type::*;
+use crate::nuts::*;
+
+/// How the suits on a board are distributed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Suitedness {
+    /// Every card is the same suit
+    Monotone,
+    /// Exactly two suits are present
+    TwoTone,
+    /// No two cards share a suit
+    Rainbow,
+    /// Three or more suits are present and at least two cards share a suit
+    Mixed,
+}
+
+/// How the ranks on a board pair up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pairing {
+    Unpaired,
+    Paired,
+    TwoPaired,
+    Trips,
+    FullHouse,
+    Quads,
+}
+
+/// A summary of a 3 to 5 card board.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::texture::*;
+///
+/// let texture = BoardTexture::new(&[NINE_HEARTS, EIGHT_HEARTS, SEVEN_CLUBS]);
+/// assert_eq!(texture.suitedness, Suitedness::TwoTone);
+/// assert_eq!(texture.pairing, Pairing::Unpaired);
+/// assert_eq!(texture.straight_combos, 3);
+/// assert!(!texture.flush_possible);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardTexture {
+    pub suitedness: Suitedness,
+    pub pairing: Pairing,
+    /// Number of cards of the board's most common suit
+    pub max_suit_count: usize,
+    /// Most distinct board ranks that fit inside a single straight
+    pub connectedness: usize,
+    /// Number of hole card rank combinations, pocket pairs included, that
+    /// make a straight on this board
+    pub straight_combos: usize,
+    /// Highest card on the board
+    pub high_card: Card,
+    /// Lowest card on the board
+    pub low_card: Card,
+    /// True if some player can hold a flush
+    pub flush_possible: bool,
+    /// True if some player can hold a straight
+    pub straight_possible: bool,
+    /// Whether the turn changed which holdings are the nuts, or `None` if
+    /// the turn hasn't been dealt
+    pub turn_changed_nuts: Option<bool>,
+    /// Whether the river changed which holdings are the nuts, or `None` if
+    /// the river hasn't been dealt
+    pub river_changed_nuts: Option<bool>,
+}
+
+impl BoardTexture {
+    /// Describe `board`, which must contain 3, 4 or 5 cards
+    pub fn new(board: &[Card]) -> BoardTexture {
+        assert!(board.len() >= 3 && board.len() <= 5, "Board must have 3 to 5 cards, found {}", board.len());
+        let cards = board.to_vec();
+
+        let suit_counts: Vec<usize> = group_by_suit(&cards).iter().map(|cards| cards.len()).collect();
+        let num_suits = suit_counts.iter().filter(|n| **n > 0).count();
+        let max_suit_count = *suit_counts.iter().max().unwrap();
+        let suitedness = match num_suits {
+            1 => Suitedness::Monotone,
+            2 => Suitedness::TwoTone,
+            _ if max_suit_count == 1 => Suitedness::Rainbow,
+            _ => Suitedness::Mixed,
+        };
+
+        let by_freq = group_by_rank_freq(&cards);
+        let pairing = if !by_freq[4].is_empty() {
+            Pairing::Quads
+        } else if !by_freq[3].is_empty() {
+            if by_freq[2].is_empty() { Pairing::Trips } else { Pairing::FullHouse }
+        } else {
+            match by_freq[2].len() {
+                0 => Pairing::Unpaired,
+                1 => Pairing::Paired,
+                _ => Pairing::TwoPaired,
+            }
+        };
+
+        let board_ranks = rank_mask(board.iter().map(|card| card.rank));
+        let connectedness = (0..STRAIGHT_WINDOWS)
+            .map(|low| (board_ranks >> low & 0b11111).count_ones() as usize)
+            .max()
+            .unwrap();
+        let mut straight_combos = 0;
+        for (i, r1) in ranks().iter().enumerate() {
+            for r2 in &ranks()[i..] {
+                if has_straight(board_ranks | rank_mask(vec![*r1, *r2])) {
+                    straight_combos += 1;
+                }
+            }
+        }
+
+        let high_card = *board.iter().max().unwrap();
+        let low_card = *board.iter().min().unwrap();
+        let changed_on = |n: usize| {
+            if board.len() > n { Some(nuts_changed(&board[..n], board[n])) } else { None }
+        };
+
+        BoardTexture{
+            suitedness,
+            pairing,
+            max_suit_count,
+            connectedness,
+            straight_combos,
+            high_card,
+            low_card,
+            flush_possible: max_suit_count >= 3,
+            straight_possible: straight_combos > 0,
+            turn_changed_nuts: changed_on(3),
+            river_changed_nuts: changed_on(4),
+        }
+    }
+}
+
+/// Number of five rank windows a straight can occupy, from A-5 up to T-A
+const STRAIGHT_WINDOWS: u32 = 10;
+
+/// Bitmask of ranks where bit `r - 1` is set for rank `r`. Aces set both bit
+/// 0 and bit 13 so they can play low.
+fn rank_mask<I: IntoIterator<Item = Rank>>(ranks: I) -> u32 {
+    let mut mask = 0;
+    for rank in ranks {
+        mask |= 1 << (rank as u32 - 1);
+        if rank == Rank::Ace {
+            mask |= 1;
+        }
+    }
+    mask
+}
+
+fn has_straight(mask: u32) -> bool {
+    (0..STRAIGHT_WINDOWS).any(|low| mask >> low & 0b11111 == 0b11111)
+}
+
+/// Return true if dealing `card` to `board` changed which holdings are the
+/// nuts. Holdings blocked by `card` are ignored on the earlier street.
+fn nuts_changed(board: &[Card], card: Card) -> bool {
+    let mut next_board = board.to_vec();
+    next_board.push(card);
+    let before: Vec<HoleCards> = NutRanking::new(board).nuts().combos.iter()
+        .filter(|combo| !combo.contains(&card))
+        .cloned()
+        .collect();
+    let after = NutRanking::new(&next_board);
+    before != after.nuts().combos
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    use super::*;
+
+    #[test]
+    fn suitedness_and_pairing() {
+        let texture = BoardTexture::new(&[ACE_SPADES, KING_SPADES, TWO_SPADES]);
+        assert_eq!(texture.suitedness, Suitedness::Monotone);
+        assert!(texture.flush_possible);
+        let texture = BoardTexture::new(&[ACE_SPADES, ACE_HEARTS, TWO_CLUBS]);
+        assert_eq!(texture.suitedness, Suitedness::Rainbow);
+        assert_eq!(texture.pairing, Pairing::Paired);
+        let texture = BoardTexture::new(&[ACE_SPADES, ACE_HEARTS, TWO_CLUBS, TWO_SPADES, ACE_DIAMONDS]);
+        assert_eq!(texture.suitedness, Suitedness::Mixed);
+        assert_eq!(texture.pairing, Pairing::FullHouse);
+        assert_eq!(texture.high_card.rank, Rank::Ace);
+        assert_eq!(texture.low_card.rank, Rank::Two);
+    }
+
+    #[test]
+    fn straights() {
+        // JT is the only straight on Q98
+        let texture = BoardTexture::new(&[QUEEN_SPADES, NINE_HEARTS, EIGHT_CLUBS]);
+        assert_eq!(texture.connectedness, 3);
+        assert_eq!(texture.straight_combos, 1);
+        // Wheel cards connect with the ace, so A42 only needs 53
+        let texture = BoardTexture::new(&[ACE_SPADES, TWO_HEARTS, FOUR_CLUBS]);
+        assert_eq!(texture.connectedness, 3);
+        assert_eq!(texture.straight_combos, 1);
+        let texture = BoardTexture::new(&[ACE_SPADES, SEVEN_HEARTS, TWO_CLUBS]);
+        assert!(!texture.straight_possible);
+        // Any ten or any five makes a straight on 9876, with T5 counted once
+        let texture = BoardTexture::new(&[NINE_SPADES, EIGHT_HEARTS, SEVEN_CLUBS, SIX_CLUBS]);
+        assert_eq!(texture.connectedness, 4);
+        assert_eq!(texture.straight_combos, 25);
+    }
+
+    #[test]
+    fn nuts_changes() {
+        let texture = BoardTexture::new(&[ACE_SPADES, KING_HEARTS, SEVEN_CLUBS]);
+        assert_eq!(texture.turn_changed_nuts, None);
+        assert_eq!(texture.river_changed_nuts, None);
+        // A brick keeps top set as the nuts, a queen makes broadway possible
+        let texture = BoardTexture::new(&[ACE_SPADES, KING_HEARTS, SEVEN_CLUBS, TWO_DIAMONDS, QUEEN_DIAMONDS]);
+        assert_eq!(texture.turn_changed_nuts, Some(false));
+        assert_eq!(texture.river_changed_nuts, Some(true));
+    }
+}
@@ -1,12 +1,79 @@
 use crate::card::{Card};
-pub fn card_vec_to_card_array(cards: &Vec<Card>) -> Option<[Card; 5]> {
+pub fn card_vec_to_card_array(cards: &[Card]) -> Option<[Card; 5]> {
     if cards.len() >= 5 {
-        Some([cards.get(0).unwrap().clone(),
-              cards.get(1).unwrap().clone(),
-              cards.get(2).unwrap().clone(),
-              cards.get(3).unwrap().clone(),
-              cards.get(4).unwrap().clone()])
+        Some([cards[0], cards[1], cards[2], cards[3], cards[4]])
     } else {
         None
     }
 }
+
+/// Return true if any card appears in both `a` and `b`
+pub fn cards_overlap(a: &[Card], b: &[Card]) -> bool {
+    a.iter().any(|card| b.contains(card))
+}
+
+/// Number of ways to choose `k` items from `n`
+pub fn num_combinations(n: usize, k: usize) -> u64 {
+    if k > n {
+        return 0;
+    }
+    let k = k.min(n - k);
+    (0..k).fold(1, |acc, i| acc * (n - i) as u64 / (i + 1) as u64)
+}
+
+/// Call `f` with every `k` card combination of `cards`, in lexicographic order
+/// of position.
+pub fn for_each_combination<F: FnMut(&[Card])>(cards: &[Card], k: usize, mut f: F) {
+    try_for_each_combination(cards, k, |combo| {
+        f(combo);
+        true
+    });
+}
+
+/// Call `f` with every `k` card combination of `cards` in the same order as
+/// `for_each_combination`, stopping as soon as `f` returns false. Returns
+/// false if it stopped early.
+pub fn try_for_each_combination<F: FnMut(&[Card]) -> bool>(cards: &[Card], k: usize, mut f: F) -> bool {
+    let mut combo: Vec<Card> = Vec::with_capacity(k);
+    combinations_from(cards, k, &mut combo, &mut f)
+}
+
+fn combinations_from<F: FnMut(&[Card]) -> bool>(cards: &[Card], k: usize, combo: &mut Vec<Card>, f: &mut F) -> bool {
+    if combo.len() == k {
+        return f(combo);
+    }
+    let needed = k - combo.len();
+    if cards.len() < needed {
+        return true;
+    }
+    for i in 0..=cards.len() - needed {
+        combo.push(cards[i]);
+        let keep_going = combinations_from(&cards[i + 1..], k, combo, f);
+        combo.pop();
+        if !keep_going {
+            return false;
+        }
+    }
+    true
+}
+
+/// Call `f` with every way of completing each of `boards` to five cards from
+/// `cards`. The boards are dealt in order and no card is dealt twice, so each
+/// board's runout comes from what is left after the boards before it.
+pub fn for_each_board_deal<F: FnMut(&[Vec<Card>])>(cards: &[Card], boards: &[Vec<Card>], mut f: F) {
+    let mut dealt: Vec<Vec<Card>> = Vec::with_capacity(boards.len());
+    board_deals_from(cards, boards, &mut dealt, &mut f);
+}
+
+fn board_deals_from<F: FnMut(&[Vec<Card>])>(cards: &[Card], boards: &[Vec<Card>], dealt: &mut Vec<Vec<Card>>, f: &mut F) {
+    let board = match boards.get(dealt.len()) {
+        Some(board) => board,
+        None => return f(dealt),
+    };
+    for_each_combination(cards, 5 - board.len(), |runout| {
+        let rest: Vec<Card> = cards.iter().filter(|card| !runout.contains(card)).copied().collect();
+        dealt.push([board, runout].concat());
+        board_deals_from(&rest, boards, dealt, f);
+        dealt.pop();
+    });
+}
@@ -0,0 +1,302 @@
+//! Describe a poker game so the equity engine can play it
+//!
+//! A `PokerVariant` spells out what an equity calculation needs to know about
+//! a game: the deck, how many hole cards each player holds, how many cards the
+//! board ends up with and how a showdown splits the pot. `variant_equity`
+//! computes equities for any game described this way, and `multiway_equity`
+//! is `variant_equity` for `Holdem`.
+//!
+//! Every equity calculation checks its cards against a variant's deck and
+//! deals its boards to the variant's board size through the same engine.
+//! Only `variant_equity` scores hands with the variant's showdown, though.
+//! The calculations built on two card `HoleCards`, such as `hand_vs_hand`,
+//! the range and folded range calculations, run it twice, multiple boards,
+//! street tables and hand strength, stay hold'em only: they rank hands with
+//! `hand_type` directly, which keeps their inner loops free of allocations.
+
+use crate::card::*;
+use crate::equity::*;
+use crate::hand_type::*;
+use crate::showdown::*;
+use crate::util::*;
+use std::cmp::Ordering;
+use std::time::Instant;
+
+/// The rules of a poker game that matter at showdown
+pub trait PokerVariant: Sync {
+    /// Every card in the deck. A full 52 card deck unless overridden.
+    fn deck(&self) -> Vec<Card> {
+        all_cards()
+    }
+
+    /// Number of hole cards dealt to each player
+    fn hole_cards(&self) -> usize;
+
+    /// Number of cards on a complete board
+    fn board_size(&self) -> usize;
+
+    /// Each player's share of the pot when `hands` show down on a complete
+    /// board, in the order the hands were given. The shares add up to one.
+    fn showdown(&self, hands: &[Vec<Card>], board: &[Card]) -> Vec<f64>;
+}
+
+/// Texas hold'em: two hole cards, a five card board and the best five cards
+/// from all seven
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Holdem;
+
+impl PokerVariant for Holdem {
+    fn hole_cards(&self) -> usize {
+        2
+    }
+
+    fn board_size(&self) -> usize {
+        5
+    }
+
+    fn showdown(&self, hands: &[Vec<Card>], board: &[Card]) -> Vec<f64> {
+        let hands: Vec<HoleCards> = hands.iter().map(|hand| [hand[0], hand[1]]).collect();
+        showdown(&hands, board).shares()
+    }
+}
+
+/// Pot limit Omaha: four hole cards, a five card board, and a hand made from
+/// exactly two hole cards and three board cards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Omaha;
+
+impl Omaha {
+    /// Best hand `hand` can make using exactly two of its hole cards and three
+    /// cards from `board`
+    pub fn best_hand(&self, hand: &[Card], board: &[Card]) -> HandType {
+        let mut best = None;
+        for_each_combination(hand, 2, |hole| {
+            for_each_combination(board, 3, |three| {
+                let made = hand_type(&[hole[0], hole[1]], three);
+                if best.as_ref().is_none_or(|best: &HandType| made.cmp(best) == Ordering::Greater) {
+                    best = Some(made);
+                }
+            });
+        });
+        best.expect("An Omaha hand needs two hole cards and three board cards")
+    }
+}
+
+impl PokerVariant for Omaha {
+    fn hole_cards(&self) -> usize {
+        4
+    }
+
+    fn board_size(&self) -> usize {
+        5
+    }
+
+    fn showdown(&self, hands: &[Vec<Card>], board: &[Card]) -> Vec<f64> {
+        let made: Vec<HandType> = hands.iter().map(|hand| self.best_hand(hand, board)).collect();
+        split_by_strength(&made)
+    }
+}
+
+/// Split a pot between the strongest of `strengths`, one per player. Games
+/// with their own hand rankings, such as lowball, can order their hands so
+/// the best hand is the greatest and split with this.
+pub fn split_by_strength<T: Ord>(strengths: &[T]) -> Vec<f64> {
+    let best = strengths.iter().max().expect("A showdown needs at least one player");
+    let winners = strengths.iter().filter(|strength| (*strength).cmp(best) == Ordering::Equal).count();
+    strengths.iter().map(|strength| if strength.cmp(best) == Ordering::Equal { 1.0 / winners as f64 } else { 0.0 }).collect()
+}
+
+/// Compute every player's equity in `variant`, completing `board` to the
+/// variant's board size from its deck according to `mode`. Cards in `dead`
+/// are never dealt.
+///
+/// A player who takes some but not all of the pot, whether by tying or by
+/// winning half of a split pot game, is counted as tying.
+///
+/// # Example
+/// ```
+/// use rusty_poker::card::*;
+/// use rusty_poker::equity::EquityMode;
+/// use rusty_poker::variant::*;
+///
+/// // A single spade makes no flush in Omaha, however many spades are on board
+/// let board = [TWO_SPADES, SEVEN_SPADES, NINE_SPADES, JACK_SPADES, KING_DIAMONDS];
+/// let hands = [vec![ACE_SPADES, THREE_CLUBS, FOUR_DIAMONDS, FIVE_HEARTS], vec![QUEEN_SPADES, TEN_SPADES, THREE_HEARTS, THREE_DIAMONDS]];
+/// let report = variant_equity(&Omaha, &hands, &board, &[], EquityMode::Exact).unwrap();
+/// assert_eq!(report.players[1].pwin, 1.0);
+/// ```
+pub fn variant_equity<V: PokerVariant>(variant: &V, hands: &[Vec<Card>], board: &[Card], dead: &[Card], mode: EquityMode) -> Result<EquityReport, EquityError> {
+    variant_equity_monitored(variant, hands, board, dead, mode, &mut Monitor::new())
+}
+
+/// Compute every player's equity in `variant` as `variant_equity` does,
+/// reporting progress to `monitor` and stopping early if it is cancelled
+pub fn variant_equity_monitored<V: PokerVariant>(variant: &V, hands: &[Vec<Card>], board: &[Card], dead: &[Card], mode: EquityMode, monitor: &mut Monitor) -> Result<EquityReport, EquityError> {
+    if hands.is_empty() {
+        return Err(EquityError::NoPlayers);
+    }
+    let start = Instant::now();
+    let deck = variant_live_deck(variant, hands, board, dead)?;
+    check_trials(mode)?;
+
+    let planned = planned_trials(deck.len(), variant.board_size() - board.len(), mode);
+    let equities = |counts: &[EquityCounts]| counts.iter().map(|counts| counts.player_equity()).collect();
+    let counts = walk_boards(variant, &deck, board, mode, || vec![EquityCounts::default(); hands.len()], |counts, full_board| {
+        let shares = variant.showdown(hands, full_board);
+        for (counts, share) in counts.iter_mut().zip(shares) {
+            counts.record(share, share < 1.0);
+        }
+    }, |counts| monitor.update(counts[0].trials, planned, || equities(counts)));
+    monitor.finish(counts[0].trials, planned, || equities(&counts));
+
+    let mut players: Vec<PlayerEquity> = equities(&counts);
+    if mode == EquityMode::Exact && !monitor.stopped {
+        for player in players.iter_mut() {
+            player.std_error = 0.0;
+        }
+    }
+    let trials = counts[0].trials;
+    Ok(EquityReport{players, counts, trials, elapsed: start.elapsed(), cancelled: monitor.stopped})
+}
+
+/// Return `variant`'s deck with every hand, board card and dead card removed.
+///
+/// Fails if a hand has the wrong number of hole cards, the board is too big,
+/// a card isn't in the deck or a card is used twice.
+pub(crate) fn variant_live_deck<V: PokerVariant, H: AsRef<[Card]>>(variant: &V, hands: &[H], board: &[Card], dead: &[Card]) -> Result<Vec<Card>, EquityError> {
+    if let Some(hand) = hands.iter().find(|hand| hand.as_ref().len() != variant.hole_cards()) {
+        return Err(EquityError::InvalidHand(hand.as_ref().len()));
+    }
+    if board.len() > variant.board_size() {
+        return Err(EquityError::InvalidBoard(board.len()));
+    }
+    let deck = variant.deck();
+    let mut used = [false; 52];
+    for card in hands.iter().flat_map(|hand| hand.as_ref()).chain(board).chain(dead) {
+        if !deck.contains(card) {
+            return Err(EquityError::CardNotInDeck(*card));
+        }
+        if used[card.index()] {
+            return Err(EquityError::DuplicateCard(*card));
+        }
+        used[card.index()] = true;
+    }
+    Ok(deck.into_iter().filter(|card| !used[card.index()]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::*;
+    use crate::simulation::{CancelToken, Simulation};
+    use super::*;
+
+    #[test]
+    fn holdem_matches_multiway_equity() {
+        // Two matching hands that mostly chop against a set, with a dead card
+        let hands = [[ACE_SPADES, KING_CLUBS], [ACE_DIAMONDS, KING_HEARTS], [SEVEN_HEARTS, SEVEN_DIAMONDS]];
+        let board = [ACE_HEARTS, KING_DIAMONDS, TWO_CLUBS];
+        let dead = [SEVEN_CLUBS];
+        let as_vecs: Vec<Vec<Card>> = hands.iter().map(|hand| hand.to_vec()).collect();
+        let generic = variant_equity(&Holdem, &as_vecs, &board, &dead, EquityMode::Exact).unwrap();
+        let holdem = multiway_equity(&hands, &board, &dead, EquityMode::Exact).unwrap();
+        assert_eq!(generic.players, holdem.players);
+        assert_eq!(generic.trials, holdem.trials);
+        assert_eq!(generic.trials, 861);
+        assert!(generic.players[0].ptie > 0.5);
+    }
+
+    #[test]
+    fn omaha_uses_exactly_two_hole_cards() {
+        // Hold'em would give the ten of spades a royal flush, but Omaha needs a
+        // second hole card and only the nine makes a straight
+        let board = [ACE_SPADES, KING_SPADES, QUEEN_SPADES, JACK_SPADES, TWO_HEARTS];
+        let straight = Omaha.best_hand(&[TEN_SPADES, NINE_CLUBS, THREE_DIAMONDS, FOUR_HEARTS], &board);
+        assert_eq!(straight.category(), HandCategory::Straight);
+        let trips = Omaha.best_hand(&[TWO_CLUBS, TWO_DIAMONDS, SEVEN_HEARTS, EIGHT_HEARTS], &board);
+        assert_eq!(trips.category(), HandCategory::Trips);
+
+        // On the turn the straight is already made, and the deuces can't use
+        // more than two board cards to improve past trips
+        let hands = [vec![TEN_SPADES, NINE_CLUBS, THREE_DIAMONDS, FOUR_HEARTS], vec![TWO_CLUBS, TWO_DIAMONDS, SEVEN_HEARTS, EIGHT_HEARTS]];
+        let report = variant_equity(&Omaha, &hands, &board[..4], &[], EquityMode::Exact).unwrap();
+        assert_eq!(report.trials, 40);
+        assert_eq!(report.players[0].pwin, 1.0);
+    }
+
+    #[test]
+    fn sampled_omaha() {
+        let hands = [vec![ACE_SPADES, ACE_CLUBS, KING_SPADES, KING_CLUBS], vec![SEVEN_HEARTS, EIGHT_HEARTS, NINE_DIAMONDS, TEN_DIAMONDS]];
+        let sim = Simulation::new(2000).seed(4);
+        let report = variant_equity(&Omaha, &hands, &[], &[], EquityMode::MonteCarlo(sim)).unwrap();
+        assert_eq!(report.trials, 2000);
+        assert!((report.players.iter().map(|player| player.equity).sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(report.players[0].std_error > 0.0);
+
+        // A cancelled calculation stops after the first batch
+        let token = CancelToken::new();
+        token.cancel();
+        let mut monitor = Monitor::new().cancel_token(token);
+        let sim = Simulation::new(1_000_000).seed(4);
+        let report = variant_equity_monitored(&Omaha, &hands, &[], &[], EquityMode::MonteCarlo(sim), &mut monitor).unwrap();
+        assert!(report.cancelled);
+        assert!(report.trials < 1_000_000);
+    }
+
+    /// Hold'em with the twos through fives taken out, enough to check that
+    /// cards are dealt from the variant's deck
+    struct SixPlus;
+
+    impl PokerVariant for SixPlus {
+        fn deck(&self) -> Vec<Card> {
+            all_cards().into_iter().filter(|card| card.rank >= Rank::Six).collect()
+        }
+
+        fn hole_cards(&self) -> usize {
+            2
+        }
+
+        fn board_size(&self) -> usize {
+            5
+        }
+
+        fn showdown(&self, hands: &[Vec<Card>], board: &[Card]) -> Vec<f64> {
+            Holdem.showdown(hands, board)
+        }
+    }
+
+    #[test]
+    fn deals_from_the_variants_deck() {
+        let hands = [vec![ACE_SPADES, ACE_CLUBS], vec![KING_SPADES, KING_CLUBS]];
+        let board = [SIX_HEARTS, SEVEN_DIAMONDS, NINE_CLUBS, JACK_HEARTS];
+        // 36 cards less four hole cards and four on the board
+        let report = variant_equity(&SixPlus, &hands, &board, &[], EquityMode::Exact).unwrap();
+        assert_eq!(report.trials, 28);
+        // Only the two remaining kings beat the aces
+        assert_eq!(report.players[1].pwin, 2.0 / 28.0);
+        let low = [SIX_HEARTS, SEVEN_DIAMONDS, TWO_CLUBS];
+        assert_eq!(variant_equity(&SixPlus, &hands, &low, &[], EquityMode::Exact).unwrap_err(), EquityError::CardNotInDeck(TWO_CLUBS));
+    }
+
+    #[test]
+    fn rejects_hands_that_dont_fit_the_game() {
+        let hands = [vec![ACE_SPADES, ACE_CLUBS], vec![KING_SPADES, KING_CLUBS]];
+        assert_eq!(variant_equity(&Omaha, &hands, &[], &[], EquityMode::Exact).unwrap_err(), EquityError::InvalidHand(2));
+        assert_eq!(split_by_strength(&[3, 7, 7]), vec![0.0, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn equal_hands_in_different_suits_split() {
+        // Both hands make the same ten high straight from different suits
+        let board = [QUEEN_CLUBS, JACK_DIAMONDS, EIGHT_HEARTS, TWO_SPADES, THREE_CLUBS];
+        let hands = [vec![TEN_SPADES, NINE_CLUBS, FOUR_DIAMONDS, FIVE_HEARTS], vec![TEN_HEARTS, NINE_DIAMONDS, FOUR_CLUBS, FIVE_SPADES]];
+        let made: Vec<HandType> = hands.iter().map(|hand| Omaha.best_hand(hand, &board)).collect();
+        assert_eq!(split_by_strength(&made), vec![0.5, 0.5]);
+        assert_eq!(Omaha.showdown(&hands, &board), vec![0.5, 0.5]);
+
+        // Suit mirrored hands have the same equity before the flop
+        let hands = [vec![ACE_SPADES, KING_CLUBS, FOUR_DIAMONDS, FIVE_HEARTS], vec![ACE_HEARTS, KING_DIAMONDS, FOUR_CLUBS, FIVE_SPADES]];
+        let sim = Simulation::new(2000).seed(6);
+        let report = variant_equity(&Omaha, &hands, &[], &[], EquityMode::MonteCarlo(sim)).unwrap();
+        assert!((report.players[0].equity - 0.5).abs() < 0.05);
+    }
+}